@@ -7,6 +7,9 @@ pub mod web;
 pub mod clean_up;
 pub mod menu;
 pub mod cursor;
+pub mod time_trial;
+pub mod enemy;
+pub mod platform;
 
 pub struct ComponentsPlugin;
 impl Plugin for ComponentsPlugin {
@@ -0,0 +1,218 @@
+use bevy::{prelude::*, sprite::Anchor};
+use bevy_ecs_ldtk::{
+    ldtk::{FieldValue, LayerInstance, TilesetDefinition},
+    prelude::LdtkEntity,
+    EntityInstance,
+};
+use bevy_rapier2d::prelude::*;
+use sprite_animation::prelude::AnimData;
+
+use crate::plugins::{enemy::EnemyAnimState, tilemap};
+
+use super::{
+    physics::{CollisionGroupsFilter, GameCollisionGroups, RigidBodyBundle},
+    tilemap::Trap,
+};
+
+const ENEMY_SPRITE_PATH: &str = "spider_sprite_sheet.png";
+const ENEMY_SPRITE_SIZE: Vec2 = Vec2::splat(32.0);
+const ENEMY_SPRITE_ROW_COL: [usize; 2] = [9, 16];
+const ENEMY_SIZE: Vec2 = Vec2::splat(24.0);
+const ENEMY_COLOR: Color = Color::rgb(0.6, 1.0, 0.6);
+const ENEMY_COLLIDER_HALF_SIZE: Vec2 = Vec2::new(4.0, 3.0);
+/// The spider is drawn in the lower half of its sprite, this lifts it onto the collider
+const ENEMY_SPRITE_ANCHOR: Vec2 = Vec2::new(0.0, -0.3);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum EnemyKind {
+    /// Walks along the ground, only ever moves horizontally on its own
+    #[default]
+    Crawler,
+    /// Ignores gravity and heads straight for its target
+    Flyer,
+}
+
+#[derive(Component, Reflect)]
+pub struct Enemy {
+    pub kind: EnemyKind,
+    pub patrol_speed: f32,
+    pub chase_speed: f32,
+    /// Players closer than this and not hidden behind terrain are chased, 0 never chases so the
+    /// enemy is a patrolling hazard
+    pub sight_range: f32,
+    /// The enemy lunges at players closer than this
+    pub attack_range: f32,
+    pub attack_impulse: f32,
+    pub attack_cooldown: f32,
+    pub attack_cooldown_counter: f32,
+    /// Seconds a web hit keeps the enemy stuck
+    pub webbed_duration: f32,
+}
+
+impl Default for Enemy {
+    fn default() -> Self {
+        Self {
+            kind: EnemyKind::default(),
+            patrol_speed: 20.0,
+            chase_speed: 45.0,
+            sight_range: 64.0,
+            attack_range: 16.0,
+            attack_impulse: 60.0,
+            attack_cooldown: 1.0,
+            attack_cooldown_counter: 0.0,
+            webbed_duration: 3.0,
+        }
+    }
+}
+
+impl From<&EntityInstance> for Enemy {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        entity_instance
+            .field_instances
+            .iter()
+            .fold(Self::default(), |enemy, field| {
+                match (field.identifier.as_str(), &field.value) {
+                    (tilemap::KIND_FIELD, FieldValue::Enum(Some(kind))) => Self {
+                        kind: match kind.as_str() {
+                            "Flyer" => EnemyKind::Flyer,
+                            _ => EnemyKind::Crawler,
+                        },
+                        ..enemy
+                    },
+                    (tilemap::SIGHT_FIELD, FieldValue::Int(Some(sight_range))) => Self {
+                        sight_range: *sight_range as f32,
+                        ..enemy
+                    },
+                    _ => enemy,
+                }
+            })
+    }
+}
+
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub enum EnemyState {
+    /// Walking the [`PatrolPath`]
+    #[default]
+    Patrol,
+    Chase(Entity),
+    /// Lunging at the player, the lunge plays out before the enemy steers again
+    Attack {
+        target: Entity,
+        remaining: f32,
+    },
+    /// Hit by a web, the enemy can't move until it breaks free
+    Webbed {
+        remaining: f32,
+    },
+}
+
+/// Points the enemy walks between in order, looping back to the first one. They're offsets from
+/// where the enemy spawned, the first one is the spawn point itself. Moving platforms and hazards
+/// follow their waypoints the same way.
+#[derive(Component, Default, Reflect)]
+pub struct PatrolPath {
+    pub points: Vec<Vec2>,
+    pub next: usize,
+    /// Translation the points are offsets from, set on the first tick
+    pub origin: Option<Vec2>,
+}
+
+impl PatrolPath {
+    /// The patrol points are authored in grid cells of the layer, LDtk counts rows downward
+    pub fn from_ldtk(entity_instance: &EntityInstance, layer_instance: &LayerInstance) -> Self {
+        let grid_size = layer_instance.grid_size as f32;
+        let size = Vec2::new(entity_instance.width as f32, entity_instance.height as f32);
+        let center =
+            entity_instance.px.as_vec2() + (Vec2::splat(0.5) - entity_instance.pivot) * size;
+        let waypoints = entity_instance
+            .field_instances
+            .iter()
+            .find(|field| field.identifier == tilemap::PATROL_FIELD)
+            .and_then(|field| match &field.value {
+                FieldValue::Points(points) => Some(points.clone()),
+                _ => None,
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .flatten()
+            .map(|cell| {
+                let offset = (cell.as_vec2() + 0.5) * grid_size - center;
+                Vec2::new(offset.x, -offset.y)
+            });
+        Self {
+            points: std::iter::once(Vec2::ZERO).chain(waypoints).collect(),
+            ..default()
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct EnemyBundle {
+    pub enemy: Enemy,
+    pub state: EnemyState,
+    pub patrol: PatrolPath,
+    /// Touching an enemy hurts like a trap does
+    pub trap: Trap,
+    pub sprite: SpriteSheetBundle,
+    pub rigid_body: RigidBodyBundle,
+    pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub active_events: ActiveEvents,
+    pub anim: AnimData<EnemyAnimState>,
+    pub name: Name,
+}
+
+impl LdtkEntity for EnemyBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        layer_instance: &LayerInstance,
+        _: Option<&Handle<Image>>,
+        _: Option<&TilesetDefinition>,
+        asset_server: &AssetServer,
+        texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let enemy = Enemy::from(entity_instance);
+        let texture_atlas = TextureAtlas::from_grid(
+            asset_server.load(ENEMY_SPRITE_PATH),
+            ENEMY_SPRITE_SIZE,
+            ENEMY_SPRITE_ROW_COL[0],
+            ENEMY_SPRITE_ROW_COL[1],
+            None,
+            None,
+        );
+        let gravity_scale = match enemy.kind {
+            EnemyKind::Crawler => 1.0,
+            EnemyKind::Flyer => 0.0,
+        };
+
+        Self {
+            enemy,
+            state: EnemyState::default(),
+            patrol: PatrolPath::from_ldtk(entity_instance, layer_instance),
+            trap: Trap::from(entity_instance.clone()),
+            sprite: SpriteSheetBundle {
+                texture_atlas: texture_atlases.add(texture_atlas),
+                sprite: TextureAtlasSprite {
+                    color: ENEMY_COLOR,
+                    custom_size: Some(ENEMY_SIZE),
+                    anchor: Anchor::Custom(ENEMY_SPRITE_ANCHOR),
+                    ..default()
+                },
+                ..default()
+            },
+            rigid_body: RigidBodyBundle {
+                rotation_constraints: LockedAxes::ROTATION_LOCKED_Z,
+                gravity_scale: GravityScale(gravity_scale),
+                ..default()
+            },
+            collider: Collider::cuboid(ENEMY_COLLIDER_HALF_SIZE.x, ENEMY_COLLIDER_HALF_SIZE.y),
+            collision_groups: CollisionGroups {
+                memberships: GameCollisionGroups::ENEMY,
+                filters: GameCollisionGroups::ENEMY.filter_group(),
+            },
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            anim: AnimData::default(),
+            name: Name::from("Enemy"),
+        }
+    }
+}
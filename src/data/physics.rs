@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use bevy::prelude::*;
 use bevy_ecs_ldtk::EntityInstance;
@@ -14,6 +14,7 @@ impl GameCollisionGroups {
     pub const WEB_STICKABLE_TERRAIN: Group = Group::GROUP_3;
     pub const WEB: Group = Group::GROUP_4;
     pub const TRAP: Group = Group::GROUP_5;
+    pub const ENEMY: Group = Group::GROUP_6;
 }
 
 pub trait CollisionGroupsFilter {
@@ -23,16 +24,39 @@ pub trait CollisionGroupsFilter {
 impl CollisionGroupsFilter for Group {
     fn filter_group(&self) -> Self {
         match *self {
-            GameCollisionGroups::PLAYER => Group::ALL ^ Group::GROUP_1 ^ Group::GROUP_4,
-            GameCollisionGroups::NON_STICK_TERRAIN => Group::GROUP_1 | Group::GROUP_4,
-            GameCollisionGroups::WEB_STICKABLE_TERRAIN => Group::GROUP_1 | Group::GROUP_4,
-            GameCollisionGroups::WEB => Group::GROUP_2 | Group::GROUP_3 | Group::GROUP_5,
+            // Webs hit players too so co-op players can tether each other
+            GameCollisionGroups::PLAYER => Group::ALL ^ Group::GROUP_1,
+            GameCollisionGroups::NON_STICK_TERRAIN => {
+                Group::GROUP_1 | Group::GROUP_4 | Group::GROUP_6
+            }
+            GameCollisionGroups::WEB_STICKABLE_TERRAIN => {
+                Group::GROUP_1 | Group::GROUP_4 | Group::GROUP_6
+            }
+            GameCollisionGroups::WEB => {
+                Group::GROUP_1 | Group::GROUP_2 | Group::GROUP_3 | Group::GROUP_5 | Group::GROUP_6
+            }
             GameCollisionGroups::TRAP => Group::GROUP_1 | Group::GROUP_4,
+            // Enemies walk on terrain and run into players and webs, but not into each other
+            GameCollisionGroups::ENEMY => {
+                Group::GROUP_1 | Group::GROUP_2 | Group::GROUP_3 | Group::GROUP_4
+            }
             _ => Group::ALL,
         }
     }
 }
 
+/// Rendered translation is blended between the last two physics ticks so movement looks smooth
+/// at frame rates that don't match the tick rate
+#[derive(Component, Clone, Debug, Default)]
+pub struct PhysicsInterpolation {
+    pub previous: Vec3,
+    pub current: Vec3,
+    /// Last translation written for rendering, anything else means the entity was moved on purpose
+    pub rendered: Vec3,
+    /// False until the first tick recorded a translation
+    pub started: bool,
+}
+
 #[derive(Clone, Debug, Default, Bundle)]
 pub struct RigidBodyBundle {
     pub rigid_body: RigidBody,
@@ -43,6 +67,7 @@ pub struct RigidBodyBundle {
     pub impulse: ExternalImpulse,
     pub complex_ef: ComplexExternalForce,
     pub damping: Damping,
+    pub interpolation: PhysicsInterpolation,
 }
 
 #[derive(Clone, Debug, Default, Bundle)]
@@ -56,26 +81,147 @@ pub struct ColliderBundle {
 impl From<EntityInstance> for ColliderBundle {
     fn from(entity_instance: EntityInstance) -> Self {
         match entity_instance.identifier.as_str() {
-            tilemap::COIN => Self {
+            tilemap::COIN | tilemap::WEB_UPGRADE => Self {
                 collider: Collider::cuboid(tilemap::TILE_HALF_SIZE.0, tilemap::TILE_HALF_SIZE.1),
                 ..default()
             },
+            tilemap::CRATE | tilemap::LEVER | tilemap::COOP_DOOR | tilemap::MOVING_PLATFORM => {
+                Self {
+                    collider: Collider::cuboid(
+                        entity_instance.width as f32 / 2.0,
+                        entity_instance.height as f32 / 2.0,
+                    ),
+                    collision_groups: CollisionGroups {
+                        memberships: GameCollisionGroups::NON_STICK_TERRAIN,
+                        filters: GameCollisionGroups::NON_STICK_TERRAIN.filter_group(),
+                    },
+                    ..default()
+                }
+            }
+            tilemap::METAL_PLATFORM => Self {
+                collider: Collider::cuboid(
+                    entity_instance.width as f32 / 2.0,
+                    entity_instance.height as f32 / 2.0,
+                ),
+                collision_groups: CollisionGroups {
+                    memberships: GameCollisionGroups::WEB_STICKABLE_TERRAIN,
+                    filters: GameCollisionGroups::WEB_STICKABLE_TERRAIN.filter_group(),
+                },
+                ..default()
+            },
+            tilemap::COOP_SWITCH => Self {
+                collider: Collider::cuboid(
+                    entity_instance.width as f32 / 2.0,
+                    entity_instance.height as f32 / 2.0,
+                ),
+                ..default()
+            },
+            tilemap::HAZARD | tilemap::MOVING_HAZARD | tilemap::FALLING_HAZARD => Self {
+                collider: Collider::cuboid(
+                    entity_instance.width as f32 / 2.0,
+                    entity_instance.height as f32 / 2.0,
+                ),
+                collision_groups: CollisionGroups {
+                    memberships: GameCollisionGroups::TRAP,
+                    filters: GameCollisionGroups::TRAP.filter_group(),
+                },
+                ..default()
+            },
             _ => Self::default(),
         }
     }
 }
 
+/// Handle to one force registered in a [`ComplexExternalForce`], handles are never reused so a
+/// stale one can't overwrite another mechanic's force. Ordered by registration.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Reflect, FromReflect,
+)]
+pub struct ForceChannel(u32);
+
+#[derive(Clone, Debug)]
+pub struct ForceChannelState {
+    pub name: &'static str,
+    pub force: Vec2,
+    /// Fraction of the force lost per second, `None` keeps it until it's set again
+    pub decay: Option<f32>,
+}
+
+/// Several named forces summed into the rapier [`ExternalForce`] of the same entity by
+/// [`crate::plugins::physics`] every tick
 #[derive(Component, Clone, Debug, Default)]
 pub struct ComplexExternalForce {
-    pub forces: HashMap<u8, Vec2>,
+    /// Kept in registration order so the forces are summed the same way in every process,
+    /// replays depend on it
+    channels: BTreeMap<ForceChannel, ForceChannelState>,
+    next_channel: u32,
 }
 
 impl ComplexExternalForce {
-    pub fn next_force(&self) -> u8 {
-        self.forces.len() as u8
+    pub fn register(&mut self, name: &'static str) -> ForceChannel {
+        self.register_channel(name, None)
+    }
+
+    pub fn register_with_decay(&mut self, name: &'static str, decay: f32) -> ForceChannel {
+        self.register_channel(name, Some(decay))
+    }
+
+    fn register_channel(&mut self, name: &'static str, decay: Option<f32>) -> ForceChannel {
+        let channel = ForceChannel(self.next_channel);
+        self.next_channel += 1;
+        self.channels.insert(
+            channel,
+            ForceChannelState {
+                name,
+                force: Vec2::ZERO,
+                decay,
+            },
+        );
+        channel
+    }
+
+    pub fn unregister(&mut self, channel: ForceChannel) {
+        self.channels.remove(&channel);
+    }
+
+    pub fn is_registered(&self, channel: ForceChannel) -> bool {
+        self.channels.contains_key(&channel)
+    }
+
+    /// Does nothing for channels that aren't registered
+    pub fn set(&mut self, channel: ForceChannel, force: Vec2) {
+        self.modify(channel, |current| *current = force);
+    }
+
+    pub fn clear(&mut self, channel: ForceChannel) {
+        self.set(channel, Vec2::ZERO);
+    }
+
+    pub fn modify(&mut self, channel: ForceChannel, f: impl FnOnce(&mut Vec2)) {
+        if let Some(state) = self.channels.get_mut(&channel) {
+            f(&mut state.force);
+        }
+    }
+
+    pub fn get(&self, channel: ForceChannel) -> Vec2 {
+        self.channels
+            .get(&channel)
+            .map_or(Vec2::ZERO, |state| state.force)
+    }
+
+    pub fn channels(&self) -> impl Iterator<Item = (ForceChannel, &ForceChannelState)> {
+        self.channels.iter().map(|(channel, state)| (*channel, state))
+    }
+
+    pub fn decay(&mut self, delta_seconds: f32) {
+        self.channels.values_mut().for_each(|state| {
+            if let Some(decay) = state.decay {
+                state.force *= (1.0 - decay).max(0.0).powf(delta_seconds);
+            }
+        });
     }
 
     pub fn composite_force(&self) -> Vec2 {
-        self.forces.values().sum()
+        self.channels.values().map(|state| state.force).sum()
     }
 }
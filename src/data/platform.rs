@@ -0,0 +1,254 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{
+    ldtk::{FieldValue, LayerInstance, TilesetDefinition},
+    prelude::LdtkEntity,
+    utils::sprite_sheet_bundle_from_entity_info,
+    EntityInstance,
+};
+use bevy_rapier2d::prelude::*;
+
+use crate::plugins::tilemap;
+
+use super::{
+    enemy::PatrolPath,
+    physics::{ColliderBundle, PhysicsInterpolation},
+    tilemap::{NonStickable, Platform, Trap, WebStickable},
+};
+
+/// Kinematic body walking its [`PatrolPath`] like an enemy patrols, used by moving platforms and
+/// swinging hazards
+#[derive(Component, Reflect)]
+pub struct MovingBody {
+    pub speed: f32,
+    /// How far the body moved last tick, players standing on it are moved by the same amount
+    pub delta: Vec2,
+}
+
+impl Default for MovingBody {
+    fn default() -> Self {
+        Self {
+            speed: 30.0,
+            delta: Vec2::ZERO,
+        }
+    }
+}
+
+impl From<&EntityInstance> for MovingBody {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        entity_instance
+            .field_instances
+            .iter()
+            .find(|field| field.identifier == tilemap::SPEED_FIELD)
+            .and_then(|field| match field.value {
+                FieldValue::Float(speed) => speed,
+                _ => None,
+            })
+            .map(|speed| Self { speed, ..default() })
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum FallingHazardState {
+    #[default]
+    Hanging,
+    Falling {
+        speed: f32,
+    },
+    /// Lying where it fell until it's put back up
+    Landed {
+        remaining: f32,
+    },
+}
+
+/// Spike hanging from the ceiling that drops once a player walks under it and hangs back up a
+/// while after landing
+#[derive(Component)]
+pub struct FallingHazard {
+    /// Players at most this far below the spike make it drop
+    pub trigger_depth: f32,
+    pub gravity: f32,
+    pub max_speed: f32,
+    /// Seconds the spike lies where it landed
+    pub reset_delay: f32,
+    pub state: FallingHazardState,
+    /// Translation the spike hangs at, set on the first tick
+    pub origin: Option<Vec2>,
+}
+
+impl Default for FallingHazard {
+    fn default() -> Self {
+        Self {
+            trigger_depth: 64.0,
+            gravity: 300.0,
+            max_speed: 200.0,
+            reset_delay: 2.0,
+            state: FallingHazardState::default(),
+            origin: None,
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct MovingPlatformBundle {
+    pub body: MovingBody,
+    pub path: PatrolPath,
+    pub platform: Platform,
+    pub non_stick: NonStickable,
+    pub sprite: SpriteSheetBundle,
+    pub rigid_body: RigidBody,
+    pub collider: ColliderBundle,
+    pub interpolation: PhysicsInterpolation,
+    pub name: Name,
+}
+
+impl LdtkEntity for MovingPlatformBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        layer_instance: &LayerInstance,
+        tileset: Option<&Handle<Image>>,
+        tileset_definition: Option<&TilesetDefinition>,
+        _: &AssetServer,
+        texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        Self {
+            body: MovingBody::from(entity_instance),
+            path: PatrolPath::from_ldtk(entity_instance, layer_instance),
+            platform: Platform,
+            non_stick: NonStickable,
+            sprite: sprite_sheet_bundle_from_entity_info(
+                entity_instance,
+                tileset,
+                tileset_definition,
+                texture_atlases,
+            ),
+            rigid_body: RigidBody::KinematicPositionBased,
+            collider: ColliderBundle::from(entity_instance.clone()),
+            interpolation: PhysicsInterpolation::default(),
+            name: Name::from("Moving Platform"),
+        }
+    }
+}
+
+/// Moving platform webs stick to, the web head moves along with it
+#[derive(Bundle)]
+pub struct MetalPlatformBundle {
+    pub body: MovingBody,
+    pub path: PatrolPath,
+    pub platform: Platform,
+    pub web_stickable: WebStickable,
+    pub sprite: SpriteSheetBundle,
+    pub rigid_body: RigidBody,
+    pub collider: ColliderBundle,
+    pub interpolation: PhysicsInterpolation,
+    pub name: Name,
+}
+
+impl LdtkEntity for MetalPlatformBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        layer_instance: &LayerInstance,
+        tileset: Option<&Handle<Image>>,
+        tileset_definition: Option<&TilesetDefinition>,
+        _: &AssetServer,
+        texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        Self {
+            body: MovingBody::from(entity_instance),
+            path: PatrolPath::from_ldtk(entity_instance, layer_instance),
+            platform: Platform,
+            web_stickable: WebStickable,
+            sprite: sprite_sheet_bundle_from_entity_info(
+                entity_instance,
+                tileset,
+                tileset_definition,
+                texture_atlases,
+            ),
+            rigid_body: RigidBody::KinematicPositionBased,
+            collider: ColliderBundle::from(entity_instance.clone()),
+            interpolation: PhysicsInterpolation::default(),
+            name: Name::from("Metal Platform"),
+        }
+    }
+}
+
+/// Trap swinging along its waypoints
+#[derive(Bundle)]
+pub struct MovingHazardBundle {
+    pub body: MovingBody,
+    pub path: PatrolPath,
+    pub trap: Trap,
+    pub non_stick: NonStickable,
+    pub sprite: SpriteSheetBundle,
+    pub rigid_body: RigidBody,
+    pub collider: ColliderBundle,
+    pub interpolation: PhysicsInterpolation,
+    pub name: Name,
+}
+
+impl LdtkEntity for MovingHazardBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        layer_instance: &LayerInstance,
+        tileset: Option<&Handle<Image>>,
+        tileset_definition: Option<&TilesetDefinition>,
+        _: &AssetServer,
+        texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        Self {
+            body: MovingBody::from(entity_instance),
+            path: PatrolPath::from_ldtk(entity_instance, layer_instance),
+            trap: Trap::from(entity_instance.clone()),
+            non_stick: NonStickable,
+            sprite: sprite_sheet_bundle_from_entity_info(
+                entity_instance,
+                tileset,
+                tileset_definition,
+                texture_atlases,
+            ),
+            rigid_body: RigidBody::KinematicPositionBased,
+            collider: ColliderBundle::from(entity_instance.clone()),
+            interpolation: PhysicsInterpolation::default(),
+            name: Name::from("Moving Hazard"),
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct FallingHazardBundle {
+    pub hazard: FallingHazard,
+    pub trap: Trap,
+    pub non_stick: NonStickable,
+    pub sprite: SpriteSheetBundle,
+    pub rigid_body: RigidBody,
+    pub collider: ColliderBundle,
+    pub interpolation: PhysicsInterpolation,
+    pub name: Name,
+}
+
+impl LdtkEntity for FallingHazardBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _: &LayerInstance,
+        tileset: Option<&Handle<Image>>,
+        tileset_definition: Option<&TilesetDefinition>,
+        _: &AssetServer,
+        texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        Self {
+            hazard: FallingHazard::default(),
+            trap: Trap::from(entity_instance.clone()),
+            non_stick: NonStickable,
+            sprite: sprite_sheet_bundle_from_entity_info(
+                entity_instance,
+                tileset,
+                tileset_definition,
+                texture_atlases,
+            ),
+            rigid_body: RigidBody::KinematicPositionBased,
+            collider: ColliderBundle::from(entity_instance.clone()),
+            interpolation: PhysicsInterpolation::default(),
+            name: Name::from("Falling Hazard"),
+        }
+    }
+}
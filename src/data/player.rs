@@ -2,22 +2,31 @@ use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use sprite_animation::prelude::AnimData;
 
-use crate::plugins::player::PlayerAnimState;
+use crate::plugins::player::{PlayerAnimState, PlayerInput};
 
-use super::physics::*;
+use super::{physics::*, tilemap::SurfaceMaterial};
 
 pub fn register(app: &mut bevy::prelude::App) {
     app.register_type::<PlayerMovement>()
         .register_type::<Player>()
         .register_type::<PlayerJump>()
-        .register_type::<PlayerInfo>();
+        .register_type::<PlayerInfo>()
+        .register_type::<PlayerHealth>()
+        .register_type::<PlayerWallJump>()
+        .register_type::<PlayerCrawl>()
+        .register_type::<PlayerAbilities>()
+        .register_type::<WebUpgrade>()
+        .register_type::<PlayerCamera>();
 }
 
 #[derive(Component, Default, Reflect)]
-pub struct Player;
+pub struct Player {
+    /// 0 for the first player, levels and time trials follow that one
+    pub index: usize,
+}
 #[derive(Component, Default, Reflect)]
 pub struct PlayerMovement {
-    pub movement_force_id: u8,
+    pub movement_force: ForceChannel,
     pub airborne_acceleration: f32,
     pub acceleration: f32,
     pub landing_accel: f32,
@@ -38,29 +47,183 @@ impl PlayerMovement {
 
 #[derive(Component, Default, Reflect)]
 pub struct PlayerJump {
-    pub jump_force_id: u8,
+    pub jump_force: ForceChannel,
     pub strength: f32,
     pub air_upward_force: f32,
 
     // After this duration system will stop refresh vec2
     pub duration: f32,
     pub counter: f32,
+
+    /// Grace period after walking off a ledge where jumping is still allowed
+    pub coyote_time: f32,
+    pub coyote_counter: f32,
+    /// A jump pressed this long before landing will still be performed on landing
+    pub buffer_time: f32,
+    pub buffer_counter: f32,
+    /// Set on jump and cleared on landing so jumping off a ledge doesn't open coyote time
+    pub is_jumping: bool,
 }
+#[derive(Component, Default, Reflect)]
+pub struct PlayerWallJump {
+    pub impulse: Vec2,
+    /// Gravity scale while sliding down ordinary terrain
+    pub slide_gravity_scale: f32,
+    /// Gravity scale while clinging to web stickable terrain
+    pub cling_gravity_scale: f32,
+    /// Gravity scale to restore when the slide ends
+    pub normal_gravity_scale: f32,
+    pub slide_max_velocity: f32,
+    pub cling_max_velocity: f32,
+
+    // Movement input is ignored for this duration after a wall jump so the
+    // impulse isn't cancelled by the player still holding toward the wall
+    pub control_lock_time: f32,
+    pub control_lock_counter: f32,
+}
+
+/// Crawling along web stickable terrain in any orientation
+#[derive(Component, Default, Reflect)]
+pub struct PlayerCrawl {
+    pub crawl_force: ForceChannel,
+    pub speed: f32,
+    /// Force pulling the player into the surface, replace gravity while crawling
+    pub stick_force: f32,
+    /// Impulse along the surface normal when jumping off
+    pub jump_strength: f32,
+    /// Points away from the surface being crawled on
+    pub surface_normal: Vec2,
+}
+
 #[derive(Component, Default, Reflect)]
 pub struct PlayerInfo {
     pub is_grounded: bool,
+    /// -1.0 for a wall on the left, 1.0 on the right and 0.0 when not touching any
+    pub wall_side: f32,
+    pub is_wall_stickable: bool,
+    pub is_wall_sliding: bool,
+    pub is_crawling: bool,
+    /// Material of the ground under the player, the default one while airborne
+    pub surface: SurfaceMaterial,
+}
+/// Optional, a player without this component dies on the first hit
+#[derive(Component, Default, Reflect)]
+pub struct PlayerHealth {
+    pub max: i32,
+    pub current: i32,
+    /// Force pushing the player away when hurt, x is flipped to push the player away from the
+    /// trap. It fades out as [`Self::knockback_force`] decays.
+    pub knockback: Vec2,
+    pub knockback_force: ForceChannel,
+
+    // While counter is above zero all damage is ignored and the sprite flickers
+    pub invulnerable_duration: f32,
+    pub invulnerable_counter: f32,
+}
+
+impl PlayerHealth {
+    pub fn new(
+        max: i32,
+        knockback: Vec2,
+        knockback_force: ForceChannel,
+        invulnerable_duration: f32,
+    ) -> Self {
+        Self {
+            max,
+            current: max,
+            knockback,
+            knockback_force,
+            invulnerable_duration,
+            invulnerable_counter: 0.0,
+        }
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable_counter > 0.0
+    }
+
+    pub fn reset(&mut self) {
+        self.current = self.max;
+        self.invulnerable_counter = 0.0;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum WebUpgrade {
+    /// Webs reach further
+    #[default]
+    LongRange,
+    /// Webs fly faster
+    FastShot,
 }
+
+/// Web upgrades unlocked so far and the web shot cooldown
+#[derive(Component, Default, Reflect)]
+pub struct PlayerAbilities {
+    pub long_range: bool,
+    pub fast_shot: bool,
+    /// Time left before another web can be shot
+    pub web_cooldown_counter: f32,
+}
+
+impl PlayerAbilities {
+    pub const LONG_RANGE_SCALE: f32 = 1.5;
+    pub const FAST_SHOT_SCALE: f32 = 1.6;
+
+    pub fn has(&self, upgrade: WebUpgrade) -> bool {
+        match upgrade {
+            WebUpgrade::LongRange => self.long_range,
+            WebUpgrade::FastShot => self.fast_shot,
+        }
+    }
+
+    /// Returns false if the upgrade was already unlocked
+    pub fn unlock(&mut self, upgrade: WebUpgrade) -> bool {
+        let unlocked = !self.has(upgrade);
+        match upgrade {
+            WebUpgrade::LongRange => self.long_range = true,
+            WebUpgrade::FastShot => self.fast_shot = true,
+        }
+        unlocked
+    }
+
+    pub fn range_scale(&self) -> f32 {
+        self.long_range
+            .then_some(Self::LONG_RANGE_SCALE)
+            .unwrap_or(1.0)
+    }
+
+    pub fn shoot_speed_scale(&self) -> f32 {
+        self.fast_shot.then_some(Self::FAST_SHOT_SCALE).unwrap_or(1.0)
+    }
+}
+
+/// Camera following the player with the same index, or every player when the camera is shared
+#[derive(Component, Default, Reflect)]
+pub struct PlayerCamera {
+    pub index: usize,
+}
+
 #[derive(Component, Default, Reflect)]
 pub struct PlayerFoot;
 #[derive(Component, Default, Reflect)]
 pub struct PlayerHead;
+#[derive(Component, Default, Reflect)]
+pub struct PlayerWall {
+    /// Which side of the player this sensor is on, -1.0 left and 1.0 right
+    pub side: f32,
+}
 
 #[derive(Bundle, Default)]
 pub struct PlayerBundle {
     pub sprite: SpriteSheetBundle,
     pub physics: RigidBodyBundle,
     pub player: Player,
+    pub input: PlayerInput,
     pub jump: PlayerJump,
+    pub wall_jump: PlayerWallJump,
+    pub crawl: PlayerCrawl,
+    pub abilities: PlayerAbilities,
     pub info: PlayerInfo,
     pub movement: PlayerMovement,
     pub name: Name,
@@ -102,3 +265,12 @@ pub struct PlayerHeadBundle {
     pub head_comp: PlayerHead,
     pub name: Name,
 }
+
+#[derive(Bundle, Default)]
+pub struct PlayerWallBundle {
+    pub transform: TransformBundle,
+    pub player_collider: PlayerColliderBundle,
+    pub sensor: Sensor,
+    pub wall_comp: PlayerWall,
+    pub name: Name,
+}
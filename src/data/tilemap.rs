@@ -1,33 +1,328 @@
-use crate::{data::physics::ColliderBundle, plugins::tilemap};
-use bevy::prelude::*;
-use bevy_ecs_ldtk::prelude::{LdtkEntity, LdtkIntCell};
-use bevy_rapier2d::prelude::{Collider, Sensor};
+use crate::{
+    data::{
+        physics::{ColliderBundle, ForceChannel, RigidBodyBundle},
+        player::WebUpgrade,
+    },
+    plugins::tilemap,
+};
+use bevy::{ecs::system::EntityCommands, prelude::*};
+use bevy_ecs_ldtk::{
+    ldtk::{FieldValue, LayerInstance},
+    prelude::{LdtkEntity, LdtkIntCell},
+    EntityInstance, IntGridCell,
+};
+use bevy_rapier2d::prelude::{Group, Sensor};
 
 #[derive(Debug, Default, Reflect, Component)]
 pub struct Platform;
 
-// Add ground, walls, etc component later;
+/// Kind of an int-grid tile, the [`IntGridTile::kind`] of the row it was spawned from.
+/// Neighbouring tiles of the same kind share their colliders.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TileKind {
+    #[default]
+    Terrain,
+    Metal,
+    Crumbling,
+    Breakable,
+    Ice,
+    Bouncy,
+    Sticky,
+    ConveyorLeft,
+    ConveyorRight,
+    SpikeUp,
+    SpikeDown,
+    SpikeLeft,
+    SpikeRight,
+    SharpSpikeUp,
+    SharpSpikeDown,
+    SharpSpikeLeft,
+    SharpSpikeRight,
+    Edge,
+    CreditsSensor,
+    EdgeSensor,
+}
 
-#[derive(Component, Default)]
-pub struct TerrainTile;
+/// Index of the row of [`tilemap::INT_GRID_TILES`] an int-grid tile was spawned from, looked up
+/// by its layer and value
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IntGridRow(pub usize);
 
-#[derive(Component, Default)]
-pub struct WebStickerTile;
+impl IntGridRow {
+    pub fn tile(self) -> &'static IntGridTile {
+        &tilemap::INT_GRID_TILES[self.0]
+    }
+}
 
-#[derive(Component, Default)]
-pub struct TrapTile;
+/// Side a spike tile points to, it only hurts from that side
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SpikeDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl SpikeDirection {
+    pub fn normal(self) -> Vec2 {
+        match self {
+            SpikeDirection::Up => Vec2::Y,
+            SpikeDirection::Down => Vec2::NEG_Y,
+            SpikeDirection::Left => Vec2::NEG_X,
+            SpikeDirection::Right => Vec2::X,
+        }
+    }
+}
+
+/// How a surface feels to stand on, put on the colliders of its [`TileKind`]. Players read the
+/// one under their feet in [`crate::data::player::PlayerInfo::surface`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct SurfaceMaterial {
+    /// Collider friction, players also lose this fraction of their speed every tick they stop
+    /// or turn around, 1.0 stops them at once
+    pub friction: f32,
+    pub restitution: f32,
+    /// Horizontal speed players standing still are carried at
+    pub conveyor_velocity: f32,
+    /// Scales how fast players walk on it
+    pub speed_scale: f32,
+}
+
+impl SurfaceMaterial {
+    pub const DEFAULT: Self = Self {
+        friction: 1.0,
+        restitution: 0.0,
+        conveyor_velocity: 0.0,
+        speed_scale: 1.0,
+    };
+    pub const ICE: Self = Self {
+        friction: 0.05,
+        ..Self::DEFAULT
+    };
+    pub const BOUNCY: Self = Self {
+        restitution: 0.9,
+        ..Self::DEFAULT
+    };
+    pub const STICKY: Self = Self {
+        speed_scale: 0.4,
+        ..Self::DEFAULT
+    };
+
+    pub const fn conveyor(velocity: f32) -> Self {
+        Self {
+            conveyor_velocity: velocity,
+            ..Self::DEFAULT
+        }
+    }
+}
+
+impl Default for SurfaceMaterial {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// How the colliders of a [`TileKind`] are spawned
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TileColliders {
+    /// Neighbouring tiles of the level are merged into as few rectangles as possible
+    Merged {
+        groups: Group,
+        material: SurfaceMaterial,
+    },
+    /// Every tile gets its own collider and sprite so it can come and go on its own
+    PerTile {
+        groups: Group,
+        material: SurfaceMaterial,
+        color: Color,
+    },
+    /// Trap shaped like the spike, a triangle per tile, or strips half a tile deep along its base
+    /// in levels merging spikes. The [`Trap`] only hurts from the side the spike points to.
+    Spike {
+        direction: SpikeDirection,
+        damage: i32,
+    },
+    /// Sensor on the tile itself
+    Sensor,
+    /// Only the marker components go on the tile
+    None,
+}
+
+/// One int-grid value of a layer and everything the tile spawns with, a new tile type only
+/// takes a row in [`tilemap::INT_GRID_TILES`]
+#[derive(Debug, Clone, Copy)]
+pub struct IntGridTile {
+    pub layer: &'static str,
+    pub value: i32,
+    /// Tiles of the same kind are merged, so rows sharing a kind have to spawn the same colliders
+    /// and components. The tilemap plugin panics when they don't.
+    pub kind: TileKind,
+    pub colliders: TileColliders,
+    /// Inserts the marker components of the tile's colliders, or of the tile itself when it has
+    /// none of its own
+    pub insert: fn(&mut EntityCommands),
+}
 
 // Components attached to connected colliders
 
 #[derive(Component, Default)]
 pub struct Terrain;
 
-#[derive(Component, Default)]
-pub struct Trap;
+#[derive(Component)]
+pub struct Trap {
+    pub damage: i32,
+    /// Side the trap hurts from, players behind its origin touch it unharmed. It hurts from every
+    /// side when `None`.
+    pub points: Option<Vec2>,
+}
+
+impl Trap {
+    pub const DEFAULT_DAMAGE: i32 = 1;
+}
+
+impl Default for Trap {
+    fn default() -> Self {
+        Self {
+            damage: Self::DEFAULT_DAMAGE,
+            points: None,
+        }
+    }
+}
+
+impl From<EntityInstance> for Trap {
+    fn from(entity_instance: EntityInstance) -> Self {
+        entity_instance
+            .field_instances
+            .iter()
+            .find(|field| field.identifier == tilemap::DAMAGE_FIELD)
+            .and_then(|field| match field.value {
+                FieldValue::Int(damage) => damage,
+                _ => None,
+            })
+            .map(|damage| Self {
+                damage,
+                ..default()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum CrumblingState {
+    #[default]
+    Solid,
+    /// Stood on, it gives way once the time is up
+    Crumbling { remaining: f32 },
+    /// Fallen away, it comes back once the time is up
+    Gone { remaining: f32 },
+}
+
+/// Tile giving way a short while after a player stands on it, it comes back later
+#[derive(Component)]
+pub struct Crumbling {
+    pub crumble_delay: f32,
+    pub respawn_delay: f32,
+    pub state: CrumblingState,
+}
+
+impl Default for Crumbling {
+    fn default() -> Self {
+        Self {
+            crumble_delay: 0.5,
+            respawn_delay: 3.0,
+            state: CrumblingState::default(),
+        }
+    }
+}
+
+/// Tile broken by a web hitting it or a player crashing into it fast enough, it stays broken
+#[derive(Component, Reflect)]
+pub struct Breakable {
+    pub break_speed: f32,
+}
+
+impl Default for Breakable {
+    fn default() -> Self {
+        Self { break_speed: 120.0 }
+    }
+}
 
 #[derive(Component, Default)]
 pub struct WebStickable;
 
+/// Object a web can catch and pull on instead of sticking to or bouncing off
+#[derive(Component, Default, Reflect)]
+pub struct WebGrabbable {
+    /// Channel of the object's [`crate::data::physics::ComplexExternalForce`] the web tension
+    /// goes to, registered the first time the object is pulled
+    pub pull_force: Option<ForceChannel>,
+}
+
+/// Grabbable switch flipped by pulling its web hard enough
+#[derive(Component, Reflect)]
+pub struct WebSwitch {
+    pub flip_tension: f32,
+    pub is_on: bool,
+    /// Whether the tension is still past [`Self::flip_tension`] since the last flip
+    pub is_pulled: bool,
+}
+
+impl Default for WebSwitch {
+    fn default() -> Self {
+        Self {
+            flip_tension: 400.0,
+            is_on: false,
+            is_pulled: false,
+        }
+    }
+}
+
+/// Pressure switch held down by a player standing on it
+#[derive(Component, Default, Reflect)]
+pub struct CoopSwitch {
+    /// Doors with the same channel open while all of its switches are held
+    pub channel: i32,
+    pub pressed_by: Option<Entity>,
+}
+
+impl From<EntityInstance> for CoopSwitch {
+    fn from(entity_instance: EntityInstance) -> Self {
+        Self {
+            channel: channel_field(&entity_instance),
+            ..default()
+        }
+    }
+}
+
+/// Door that only opens while every [`CoopSwitch`] of its channel is held by a different player.
+/// Without a second player it stays open, co-op puzzles don't block single player runs.
+#[derive(Component, Default, Reflect)]
+pub struct CoopDoor {
+    pub channel: i32,
+    pub is_open: bool,
+}
+
+impl From<EntityInstance> for CoopDoor {
+    fn from(entity_instance: EntityInstance) -> Self {
+        Self {
+            channel: channel_field(&entity_instance),
+            ..default()
+        }
+    }
+}
+
+fn channel_field(entity_instance: &EntityInstance) -> i32 {
+    entity_instance
+        .field_instances
+        .iter()
+        .find(|field| field.identifier == tilemap::CHANNEL_FIELD)
+        .and_then(|field| match field.value {
+            FieldValue::Int(channel) => channel,
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
 #[derive(Component, Default)]
 pub struct NonStickable;
 
@@ -46,92 +341,169 @@ pub struct Coin;
 #[derive(Component, Default)]
 pub struct Credits;
 
-#[derive(Bundle, Default, LdtkIntCell)]
-pub struct TerrainTileBundle {
-    tile: TerrainTile,
+#[derive(Component, Default)]
+pub struct WebUpgradePickup {
+    pub upgrade: WebUpgrade,
+}
+
+impl From<EntityInstance> for WebUpgradePickup {
+    fn from(entity_instance: EntityInstance) -> Self {
+        entity_instance
+            .field_instances
+            .iter()
+            .find(|field| field.identifier == tilemap::UPGRADE_FIELD)
+            .and_then(|field| match field.value {
+                FieldValue::Enum(Some(ref upgrade)) => match upgrade.as_str() {
+                    "LongRange" => Some(WebUpgrade::LongRange),
+                    "FastShot" => Some(WebUpgrade::FastShot),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .map(|upgrade| Self { upgrade })
+            .unwrap_or_default()
+    }
 }
 
-#[derive(Bundle, Default, LdtkIntCell)]
-pub struct WebStickerTileBundle {
-    web_sticker: WebStickerTile,
+/// Every registered int-grid cell, [`crate::plugins::tilemap::spawn`] gives it colliders by its
+/// [`TileKind`]
+#[derive(Bundle, Default)]
+pub struct IntGridTileBundle {
+    kind: TileKind,
+    row: IntGridRow,
 }
 
-#[derive(Bundle, Default, LdtkIntCell)]
-pub struct TrapTileBundle {
-    trap: TrapTile,
+impl LdtkIntCell for IntGridTileBundle {
+    fn bundle_int_cell(int_grid_cell: IntGridCell, layer_instance: &LayerInstance) -> Self {
+        let row = tilemap::INT_GRID_TILES
+            .iter()
+            .position(|tile| {
+                tile.layer == layer_instance.identifier && tile.value == int_grid_cell.value
+            })
+            .map(IntGridRow)
+            .expect("Only int-grid values from the table are registered");
+        Self {
+            kind: row.tile().kind,
+            row,
+        }
+    }
 }
 
-#[derive(Bundle, Default, LdtkIntCell)]
+#[derive(Bundle, Default)]
 pub struct TerrainBundle {
     tile: Terrain,
     platform: Platform,
     non_stick: NonStickable,
 }
 
-#[derive(Bundle, Default, LdtkIntCell)]
+#[derive(Bundle, Default)]
 pub struct WebStickerBundle {
     web_sticker: WebStickable,
     platform: Platform,
 }
 
-#[derive(Bundle, Default, LdtkIntCell)]
+#[derive(Bundle, Default)]
 pub struct TrapBundle {
     trap: Trap,
     non_stick: NonStickable,
 }
 
+impl TrapBundle {
+    pub fn new(trap: Trap) -> Self {
+        Self { trap, ..default() }
+    }
+}
+
+#[derive(Bundle, Default)]
+pub struct CrumblingBundle {
+    crumbling: Crumbling,
+    platform: Platform,
+    non_stick: NonStickable,
+}
+
 #[derive(Bundle, Default)]
-pub struct EdgeSensorBundle {
-    esen: EdgeSensor,
+pub struct BreakableBundle {
+    breakable: Breakable,
+    platform: Platform,
+    non_stick: NonStickable,
+}
+
+#[derive(Bundle, Default, LdtkEntity)]
+pub struct CoinBundle {
+    coin: Coin,
+    #[sprite_sheet_bundle]
+    #[bundle]
+    sprite: SpriteSheetBundle,
+    #[from_entity_instance]
     collider: ColliderBundle,
     sensor: Sensor,
 }
 
-impl LdtkIntCell for EdgeSensorBundle {
-    fn bundle_int_cell(
-        _: bevy_ecs_ldtk::IntGridCell,
-        _: &bevy_ecs_ldtk::prelude::LayerInstance,
-    ) -> Self {
-        Self {
-            collider: ColliderBundle {
-                collider: Collider::cuboid(tilemap::TILE_HALF_SIZE.0, tilemap::TILE_HALF_SIZE.1),
-                ..default()
-            },
-            ..default()
-        }
-    }
+/// Trap placed as an entity so its size and damage can be authored in LDtk
+#[derive(Bundle, Default, LdtkEntity)]
+pub struct HazardBundle {
+    #[from_entity_instance]
+    trap: Trap,
+    non_stick: NonStickable,
+    #[from_entity_instance]
+    collider: ColliderBundle,
 }
 
-#[derive(Bundle, Default)]
-pub struct CreditsSensorBundle {
-    ssen: CreditsSensor,
+/// Dynamic box the player can stand on or drag around with the web
+#[derive(Bundle, Default, LdtkEntity)]
+pub struct CrateBundle {
+    grabbable: WebGrabbable,
+    platform: Platform,
+    non_stick: NonStickable,
+    #[sprite_sheet_bundle]
+    #[bundle]
+    sprite: SpriteSheetBundle,
+    #[bundle]
+    rigid_body: RigidBodyBundle,
+    #[from_entity_instance]
     collider: ColliderBundle,
-    sensor: Sensor,
 }
 
-impl LdtkIntCell for CreditsSensorBundle {
-    fn bundle_int_cell(
-        _: bevy_ecs_ldtk::IntGridCell,
-        _: &bevy_ecs_ldtk::prelude::LayerInstance,
-    ) -> Self {
-        Self {
-            collider: ColliderBundle {
-                collider: Collider::cuboid(tilemap::TILE_HALF_SIZE.0, tilemap::TILE_HALF_SIZE.1),
-                ..default()
-            },
-            ..default()
-        }
-    }
+#[derive(Bundle, Default, LdtkEntity)]
+pub struct LeverBundle {
+    grabbable: WebGrabbable,
+    switch: WebSwitch,
+    #[sprite_sheet_bundle]
+    #[bundle]
+    sprite: SpriteSheetBundle,
+    #[from_entity_instance]
+    collider: ColliderBundle,
 }
 
-#[derive(Bundle, Default, LdtkIntCell)]
-pub struct EdgeBundle {
-    edge: Edge,
+#[derive(Bundle, Default, LdtkEntity)]
+pub struct CoopSwitchBundle {
+    #[from_entity_instance]
+    switch: CoopSwitch,
+    #[sprite_sheet_bundle]
+    #[bundle]
+    sprite: SpriteSheetBundle,
+    #[from_entity_instance]
+    collider: ColliderBundle,
+    sensor: Sensor,
 }
 
 #[derive(Bundle, Default, LdtkEntity)]
-pub struct CoinBundle {
-    coin: Coin,
+pub struct CoopDoorBundle {
+    #[from_entity_instance]
+    door: CoopDoor,
+    platform: Platform,
+    non_stick: NonStickable,
+    #[sprite_sheet_bundle]
+    #[bundle]
+    sprite: SpriteSheetBundle,
+    #[from_entity_instance]
+    collider: ColliderBundle,
+}
+
+#[derive(Bundle, Default, LdtkEntity)]
+pub struct WebUpgradeBundle {
+    #[from_entity_instance]
+    pickup: WebUpgradePickup,
     #[sprite_sheet_bundle]
     #[bundle]
     sprite: SpriteSheetBundle,
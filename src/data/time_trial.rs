@@ -0,0 +1,13 @@
+use bevy::prelude::*;
+
+/// Translucent spider replaying the best run through a level
+#[derive(Component, Default)]
+pub struct Ghost {
+    pub level: usize,
+}
+
+/// Split time shown for a moment after finishing a level
+#[derive(Component)]
+pub struct SplitText {
+    pub timer: Timer,
+}
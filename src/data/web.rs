@@ -1,17 +1,74 @@
 use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
 use bevy_rapier2d::prelude::*;
 
+use super::{physics::ForceChannel, tilemap::Platform};
+
+/// One straight piece of the web, segment 0 starts at the web head
 #[derive(Component, Default, Reflect)]
-pub struct WebString;
+pub struct WebString {
+    pub segment: usize,
+}
 
 #[derive(Component, Default, Reflect)]
 pub struct WebHead;
 
+/// Line from the player toward the cursor showing whether a shot would stick
+#[derive(Component, Reflect)]
+pub struct WebAimPreview {
+    pub player: Entity,
+}
+
 #[derive(Component, Default, Reflect)]
 pub struct Web {
+    /// Increases with every shot, the oldest web is dropped first once the limit is reached
+    pub id: u32,
+    /// Player who shot the web, swinging and reeling only affect that one
+    pub owner: Option<Entity>,
     pub attached: bool,
-    pub pull_force_id: Option<u8>,
+    pub pull_force: Option<ForceChannel>,
+    /// Set when the web attaches, reeling in or out changes it afterwards
     pub initial_web_length: Option<f32>,
+    /// Terrain corners the web wraps around, ordered from the web head to the player
+    pub bend_points: Vec<Vec2>,
+    /// Second stuck end once the web is turned into a bridge, it no longer follows the player
+    pub anchor: Option<Vec2>,
+    /// [`crate::data::tilemap::WebGrabbable`] object the web head is stuck to
+    pub grabbed: Option<Entity>,
+    /// Where the web head sits in the grabbed object's or moving platform's local space
+    pub grab_offset: Vec3,
+    /// [`crate::data::platform::MovingBody`] the web head is stuck to, it's carried along with it
+    pub platform: Option<Entity>,
+    /// Other player the web head is stuck to, the tension pulls both players toward each other
+    pub tethered: Option<Entity>,
+    /// Channel of the tethered player's [`crate::data::physics::ComplexExternalForce`] the
+    /// tension goes to, registered the first time the tethered player is pulled
+    pub tether_force: Option<ForceChannel>,
+    /// Seconds since the web attached
+    pub attached_time: f32,
+}
+
+impl Web {
+    pub fn is_bridge(&self) -> bool {
+        self.anchor.is_some()
+    }
+
+    /// The point the player swings around, either the last bend or the web head itself
+    pub fn pivot(&self, web_head_translation: Vec2) -> Vec2 {
+        self.bend_points
+            .last()
+            .copied()
+            .unwrap_or(web_head_translation)
+    }
+
+    /// Length of the web already wrapped around terrain between the web head and the pivot
+    pub fn wrapped_length(&self, web_head_translation: Vec2) -> f32 {
+        self.bend_points
+            .iter()
+            .fold((0.0, web_head_translation), |(length, previous), bend| {
+                (length + previous.distance(*bend), *bend)
+            })
+            .0
+    }
 }
 
 #[derive(Bundle, Default)]
@@ -32,3 +89,18 @@ pub struct WebHeadBundle {
     pub collision_groups: CollisionGroups,
     pub active_events: ActiveEvents,
 }
+
+/// Solid part of a web bridge the player can walk and bounce on
+#[derive(Component, Default, Reflect)]
+pub struct WebBridge;
+
+#[derive(Bundle, Default)]
+pub struct WebBridgeBundle {
+    pub web_bridge: WebBridge,
+    pub platform: Platform,
+    pub transform: TransformBundle,
+    pub collider: Collider,
+    pub rigid_body: RigidBody,
+    pub restitution: Restitution,
+    pub collision_groups: CollisionGroups,
+}
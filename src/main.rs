@@ -3,16 +3,32 @@ use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
 use iyes_loopless::prelude::AppLooplessStateExt;
 use plugins::{
-    audio::AudioPlugin, base::BasePlugin, clean_up::CleanUpPlugin, debug::DebugPlugin,
-    display::DisplayPlugin, menu::MenuPlugin, physics::PhysicsPlugin, player::PlayerPlugin,
+    audio::AudioPlugin,
+    base::{BasePlugin, CameraMode, CameraPlugin, HeadlessPlugin},
+    clean_up::CleanUpPlugin,
+    debug::DebugPlugin,
+    display::DisplayPlugin,
+    enemy::EnemyPlugin,
+    menu::MenuPlugin,
+    physics::PhysicsPlugin,
+    player::PlayerPlugin,
+    replay::{ReplayMode, ReplayPlugin},
     tilemap::TilemapPlugin,
+    time_trial::TimeTrialPlugin,
 };
 use strum::EnumIter;
 
 mod data;
 mod plugins;
+#[cfg(test)]
+mod tests;
 mod utils;
 
+/// Second local player on the arrow keys
+const COOP_ARG: &str = "--coop";
+/// One camera per player instead of a shared one
+const SPLIT_ARG: &str = "--split";
+
 #[derive(Hash, Debug, Eq, PartialEq, Clone, Copy, EnumIter)]
 pub enum GameState {
     InMenu,
@@ -21,31 +37,56 @@ pub enum GameState {
 
 fn main() {
     let mut app = App::new();
+    let replay_mode = ReplayMode::from_args();
+    let headless = replay_mode.as_ref().map_or(false, ReplayMode::is_headless);
+    let has_arg = |flag: &str| std::env::args().any(|arg| arg == flag);
+    let player_count = has_arg(COOP_ARG).then_some(2).unwrap_or(1);
+    let camera_mode = match has_arg(SPLIT_ARG) {
+        true => CameraMode::Split,
+        false => CameraMode::Shared,
+    };
 
     app
         // Resources
-        .add_loopless_state(GameState::InMenu)
+        // Recorded runs skip the menu so they all start the same way
+        .add_loopless_state(match replay_mode.is_some() {
+            true => GameState::InGame,
+            false => GameState::InMenu,
+        })
         .insert_resource(ClearColor(Color::Rgba {
             red: 0.2,
             green: 0.2,
             blue: 0.3,
             alpha: 1.,
-        }))
-        // Systems, testing purpose
-        // Plugins
-        .add_plugin(BasePlugin)
-        .add_plugin(PhysicsPlugin)
-        .add_plugin(DisplayPlugin)
-        .add_plugin(AudioPlugin)
+        }));
+
+    // Systems, testing purpose
+    // Plugins
+    match headless {
+        true => app.add_plugin(HeadlessPlugin).add_plugin(PhysicsPlugin),
+        false => app
+            .add_plugin(BasePlugin)
+            .add_plugin(PhysicsPlugin)
+            .add_plugin(DisplayPlugin)
+            .add_plugin(AudioPlugin),
+    };
+    app.add_plugin(CameraPlugin::default().with_mode(camera_mode))
         // This will also clean up debug line, I can't do anything about that
         // because bevy decided that the component used to mark that is pub(crate)
         .add_plugin(CleanUpPlugin::<GameState>::default())
-        .add_plugin(MenuPlugin::new(Some(GameState::InMenu)))
         .add_plugin(TilemapPlugin::new(Some(GameState::InGame)))
-        .add_plugin(PlayerPlugin::new(Some(GameState::InGame)));
+        .add_plugin(PlayerPlugin::new(Some(GameState::InGame)).with_player_count(player_count))
+        .add_plugin(EnemyPlugin::new(Some(GameState::InGame)));
+    (!headless).then(|| {
+        app.add_plugin(MenuPlugin::new(Some(GameState::InMenu)))
+            .add_plugin(TimeTrialPlugin::new(Some(GameState::InGame)))
+    });
+    if let Some(replay_mode) = replay_mode {
+        app.add_plugin(ReplayPlugin::new(replay_mode, Some(GameState::InGame)));
+    }
 
     #[cfg(debug_assertions)]
-    app.add_plugin(WorldInspectorPlugin).add_plugin(DebugPlugin);
+    (!headless).then(|| app.add_plugin(WorldInspectorPlugin).add_plugin(DebugPlugin));
 
     app.run();
 }
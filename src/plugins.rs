@@ -5,6 +5,9 @@ pub mod physics;
 pub mod tilemap;
 pub mod utils;
 pub mod player;
+pub mod enemy;
 pub mod debug;
 pub mod clean_up;
-pub mod menu;
\ No newline at end of file
+pub mod menu;
+pub mod replay;
+pub mod time_trial;
\ No newline at end of file
@@ -1,14 +1,46 @@
-use crate::data::clean_up::Persist;
+use std::time::{Duration, Instant};
 
-use super::display::DisplaySettings;
+use crate::data::{
+    clean_up::Persist,
+    player::{Player, PlayerCamera},
+};
+
+use super::{display::DisplaySettings, physics::FIXED_TIMESTEP};
 use bevy::{
+    app::{ScheduleRunnerPlugin, ScheduleRunnerSettings},
+    core_pipeline::clear_color::ClearColorConfig,
     prelude::*,
-    window::{WindowDescriptor, WindowPlugin, WindowId},
-    DefaultPlugins, winit::WinitWindows,
+    render::{camera::Viewport, settings::WgpuSettings},
+    time::TimePlugin,
+    window::{WindowDescriptor, WindowId, WindowPlugin},
+    winit::{WinitPlugin, WinitWindows},
+    DefaultPlugins,
 };
 use winit::window::Icon;
+
+/// Window, icon and the rest of bevy's default plugins
 pub struct BasePlugin;
 
+/// [`BasePlugin`] without a window or a renderer, for replays on machines without a display.
+/// Every update is one physics tick and runs right after the last one, faster than real time.
+pub struct HeadlessPlugin;
+
+/// The cameras gameplay follows the players with, needed with or without a window
+#[derive(Default)]
+pub struct CameraPlugin {
+    mode: CameraMode,
+}
+
+/// How local co-op players share the window
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    /// One camera following the midpoint of every player
+    #[default]
+    Shared,
+    /// One camera per player, side by side
+    Split,
+}
+
 impl Plugin for BasePlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         let default_settings = DisplaySettings::default();
@@ -35,8 +67,45 @@ impl Plugin for BasePlugin {
     }
 }
 
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.insert_resource(WgpuSettings {
+            backends: None,
+            ..default()
+        })
+        .insert_resource(ScheduleRunnerSettings::run_loop(Duration::ZERO))
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    add_primary_window: false,
+                    exit_on_all_closed: false,
+                    ..Default::default()
+                })
+                .set(ImagePlugin::default_nearest())
+                .disable::<WinitPlugin>()
+                .disable::<TimePlugin>(),
+        )
+        .add_plugin(ScheduleRunnerPlugin)
+        .init_resource::<Time>()
+        .add_system_to_stage(CoreStage::First, advance_time_by_one_tick);
+    }
+}
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.insert_resource(self.mode)
+            .add_startup_system(spawn_camera)
+            .add_system(update_split_cameras);
+    }
+}
+impl CameraPlugin {
+    pub fn with_mode(mut self, mode: CameraMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
 
-fn setup(mut commands: Commands,  windows: NonSend<WinitWindows>) {
+fn setup(windows: NonSend<WinitWindows>) {
     let primary = windows.get_window(WindowId::primary()).unwrap();
     // primary.set_cursor_visible(false);
 
@@ -51,9 +120,81 @@ fn setup(mut commands: Commands,  windows: NonSend<WinitWindows>) {
 
     let icon = Icon::from_rgba(icon_rgba, icon_width, icon_height).unwrap();
     primary.set_window_icon(Some(icon));
+}
+
+fn spawn_camera(mut commands: Commands) {
+    spawn_player_camera(&mut commands, 0);
+}
 
+fn spawn_player_camera(commands: &mut Commands, index: usize) {
+    // Later cameras draw over their part of the window without clearing the others
+    let mut camera_bundle = Camera2dBundle::default();
+    if index > 0 {
+        camera_bundle.camera.priority = index as isize;
+        camera_bundle.camera_2d.clear_color = ClearColorConfig::None;
+    }
     commands
-        .spawn(Camera2dBundle::default())
+        .spawn(camera_bundle)
+        .insert(PlayerCamera { index })
         .insert(Persist)
-        .insert(Name::from("Camera"));
-}
\ No newline at end of file
+        .insert(Name::from(match index {
+            0 => String::from("Camera"),
+            _ => format!("Camera {}", index + 1),
+        }));
+}
+
+/// Keep one camera per player in [`CameraMode::Split`], each drawing to its own column of the
+/// window. Without a window, e.g. in tests, the cameras are still spawned but have no viewport.
+fn update_split_cameras(
+    camera_mode: Res<CameraMode>,
+    windows: Res<Windows>,
+    q_player: Query<&Player>,
+    mut q_camera: Query<(Entity, &PlayerCamera, &mut Camera)>,
+    mut commands: Commands,
+) {
+    let camera_count = match *camera_mode {
+        CameraMode::Shared => 1,
+        CameraMode::Split => q_player
+            .iter()
+            .map(|player| player.index + 1)
+            .max()
+            .unwrap_or(1),
+    };
+
+    (0..camera_count)
+        .filter(|index| !q_camera.iter().any(|(_, camera, _)| camera.index == *index))
+        .for_each(|index| spawn_player_camera(&mut commands, index));
+
+    let window = windows.get_primary();
+    q_camera.for_each_mut(|(entity, player_camera, mut camera)| {
+        if player_camera.index >= camera_count {
+            commands.entity(entity).despawn_recursive();
+            return;
+        }
+        let viewport = window.filter(|_| camera_count > 1).map(|window| {
+            let width = window.physical_width() / camera_count as u32;
+            Viewport {
+                physical_position: UVec2::new(player_camera.index as u32 * width, 0),
+                physical_size: UVec2::new(width, window.physical_height()),
+                ..default()
+            }
+        });
+        let unchanged = match (&camera.viewport, &viewport) {
+            (Some(current), Some(viewport)) => {
+                current.physical_position == viewport.physical_position
+                    && current.physical_size == viewport.physical_size
+            }
+            (current, viewport) => current.is_none() && viewport.is_none(),
+        };
+        (!unchanged).then(|| camera.viewport = viewport);
+    });
+}
+
+/// Stand in for the time plugin, every update is one tick later than the last
+pub fn advance_time_by_one_tick(mut time: ResMut<Time>, mut now: Local<Option<Instant>>) {
+    let next = now.map_or_else(Instant::now, |now| {
+        now + Duration::from_secs_f32(FIXED_TIMESTEP)
+    });
+    *now = Some(next);
+    time.update_with_instant(next);
+}
@@ -1,8 +1,16 @@
+use std::collections::HashSet;
+
 use bevy::prelude::*;
 use bevy_rapier2d::render::RapierDebugRenderPlugin;
 use iyes_loopless::state::{CurrentState, NextState};
 
-use crate::GameState;
+use crate::{
+    data::physics::{ComplexExternalForce, ForceChannel},
+    GameState,
+};
+
+const FORCE_ARROW_SCALE: f32 = 0.02;
+const FORCE_ARROW_Z: f32 = 200.0;
 
 // Add debug codes, systems without spreading it everywhere
 
@@ -10,11 +18,24 @@ pub struct DebugPlugin;
 impl Plugin for DebugPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         #[cfg(debug_assertions)]
-        app.add_system(switch_state)
+        app.insert_resource(ShowForceChannels::default())
+            .add_system(switch_state)
+            .add_system(toggle_force_channels)
+            .add_system(draw_force_channels)
             .add_plugin(RapierDebugRenderPlugin::default());
     }
 }
 
+#[derive(Resource, Default)]
+struct ShowForceChannels(bool);
+
+/// Line from a body showing the current value of one of its force channels
+#[derive(Component)]
+struct ForceChannelArrow {
+    body: Entity,
+    channel: ForceChannel,
+}
+
 fn switch_state(
     mut commands: Commands,
     input: Res<Input<KeyCode>>,
@@ -31,3 +52,64 @@ fn switch_state(
     info!("Changing to state {:?}", next_state.0);
     commands.insert_resource(next_state);
 }
+
+fn toggle_force_channels(input: Res<Input<KeyCode>>, mut show: ResMut<ShowForceChannels>) {
+    input.just_pressed(KeyCode::F3).then(|| show.0 = !show.0);
+}
+
+fn draw_force_channels(
+    show: Res<ShowForceChannels>,
+    q_cef: Query<(Entity, &GlobalTransform, &ComplexExternalForce)>,
+    mut q_arrow: Query<(Entity, &ForceChannelArrow, &mut Transform)>,
+    mut commands: Commands,
+) {
+    let mut drawn = HashSet::new();
+    q_arrow.for_each_mut(|(entity, arrow, mut transform)| {
+        let body_force = q_cef
+            .get(arrow.body)
+            .ok()
+            .filter(|(_, _, cef)| cef.is_registered(arrow.channel))
+            .map(|(_, body_transform, cef)| {
+                (body_transform.translation().truncate(), cef.get(arrow.channel))
+            });
+        let (true, Some((origin, force))) = (show.0, body_force) else {
+            commands.entity(entity).despawn_recursive();
+            return;
+        };
+        let end = origin + force * FORCE_ARROW_SCALE;
+        transform.translation = ((origin + end) / 2.0).extend(FORCE_ARROW_Z);
+        transform.rotation = Quat::from_rotation_z(force.y.atan2(force.x));
+        transform.scale = Vec3::new(origin.distance(end), 0.5, 1.0);
+        drawn.insert((arrow.body, arrow.channel));
+    });
+    if !show.0 {
+        return;
+    }
+
+    // Same name, same color, so a channel is recognizable on every body
+    let channel_color = |name: &str| {
+        let hue = name
+            .bytes()
+            .fold(0u32, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as u32))
+            % 360;
+        Color::hsl(hue as f32, 0.8, 0.6)
+    };
+    q_cef.for_each(|(body, _, cef)| {
+        cef.channels()
+            .filter(|(channel, _)| !drawn.contains(&(body, *channel)))
+            .for_each(|(channel, state)| {
+                commands
+                    .spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color: channel_color(state.name),
+                            custom_size: Some(Vec2::ONE),
+                            ..default()
+                        },
+                        transform: Transform::from_scale(Vec3::ZERO),
+                        ..default()
+                    })
+                    .insert(ForceChannelArrow { body, channel })
+                    .insert(Name::from(format!("Force channel {}", state.name)));
+            });
+    });
+}
@@ -0,0 +1,67 @@
+mod ai;
+mod animation;
+mod webbed;
+
+use bevy::prelude::*;
+
+use crate::{
+    plugins::physics::{FixedEventExt, FixedUpdateStage},
+    utils::state_helper::StateExtend,
+    GameState,
+};
+
+use self::{
+    ai::{move_enemies, update_enemy_state},
+    animation::EnemyAnimationPlugin,
+    webbed::web_enemies,
+};
+
+/// Labels of the enemy systems running every tick, in the order they run
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum EnemySystem {
+    WebEnemies,
+    UpdateEnemyState,
+    MoveEnemies,
+}
+
+#[derive(Eq, Hash, PartialEq, Default, Clone, Copy, Debug)]
+pub enum EnemyAnimState {
+    #[default]
+    Walking,
+    Chasing,
+    Attacking,
+    Webbed,
+}
+
+#[derive(Debug)]
+pub enum EnemyEvent {
+    /// Enemy [Entity] started chasing the player [Entity]
+    Spotted(Entity, Entity),
+    /// Enemy [Entity] lunged at the player [Entity]
+    Attacked(Entity, Entity),
+    Webbed(Entity),
+    BrokeFree(Entity),
+}
+
+/// LDtk placed [`crate::data::enemy::Enemy`] entities patrolling, chasing and lunging at players.
+/// Touching one hurts like a trap, so deaths go through [`super::player::PlayerEvent::Died`].
+pub struct EnemyPlugin {
+    run_in: Option<GameState>,
+}
+
+impl EnemyPlugin {
+    pub fn new(run_in: Option<GameState>) -> Self {
+        Self { run_in }
+    }
+}
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_fixed_event::<EnemyEvent>()
+            .add_plugin(EnemyAnimationPlugin::new(self.run_in));
+        app.add_fixed_chain_run_if(self.run_in, FixedUpdateStage::Enemies)
+            .then(EnemySystem::WebEnemies, web_enemies)
+            .then(EnemySystem::UpdateEnemyState, update_enemy_state)
+            .then(EnemySystem::MoveEnemies, move_enemies);
+    }
+}
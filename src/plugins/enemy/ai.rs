@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    data::{
+        enemy::{Enemy, EnemyKind, EnemyState, PatrolPath},
+        physics::GameCollisionGroups,
+        player::Player,
+    },
+    plugins::physics::FIXED_TIMESTEP,
+};
+
+use super::EnemyEvent;
+
+/// Close enough to a patrol point to head for the next one
+const ARRIVE_DISTANCE: f32 = 2.0;
+/// Seconds a lunge plays out before the enemy steers again
+const ATTACK_TIME: f32 = 0.4;
+/// Chasing enemies only give up once the player is this much further than their sight range
+const LOSE_SIGHT_SCALE: f32 = 1.5;
+
+/// Switch enemies between patrolling, chasing and attacking the closest player they can see, and
+/// count down how long webbed enemies stay stuck
+pub fn update_enemy_state(
+    mut q_enemy: Query<(
+        Entity,
+        &GlobalTransform,
+        &mut Enemy,
+        &mut EnemyState,
+        &mut ExternalImpulse,
+    )>,
+    q_player: Query<(Entity, &GlobalTransform), With<Player>>,
+    rapier_context: Res<RapierContext>,
+    mut evw_enemy: EventWriter<EnemyEvent>,
+) {
+    let filter = QueryFilter::new()
+        .exclude_sensors()
+        .groups(CollisionGroups::new(
+            GameCollisionGroups::ENEMY,
+            GameCollisionGroups::NON_STICK_TERRAIN | GameCollisionGroups::WEB_STICKABLE_TERRAIN,
+        ));
+    let is_visible = |from: Vec2, to: Vec2| {
+        let distance = from.distance(to);
+        distance <= f32::EPSILON
+            || rapier_context
+                .cast_ray(from, (to - from) / distance, distance, true, filter)
+                .is_none()
+    };
+
+    q_enemy.for_each_mut(|(entity, transform, mut enemy, mut state, mut impulse)| {
+        let translation = transform.translation().truncate();
+        enemy.attack_cooldown_counter = (enemy.attack_cooldown_counter - FIXED_TIMESTEP).max(0.0);
+
+        let sight_range = match *state {
+            EnemyState::Chase(_) => enemy.sight_range * LOSE_SIGHT_SCALE,
+            _ => enemy.sight_range,
+        };
+        let closest_player = || {
+            q_player
+                .iter()
+                .map(|(player, transform)| (player, transform.translation().truncate()))
+                .filter(|(_, player_translation)| {
+                    player_translation.distance(translation) <= sight_range
+                        && is_visible(translation, *player_translation)
+                })
+                .min_by(|(_, a), (_, b)| {
+                    a.distance(translation).total_cmp(&b.distance(translation))
+                })
+        };
+
+        let next_state = match *state {
+            EnemyState::Webbed { remaining } if remaining > FIXED_TIMESTEP => EnemyState::Webbed {
+                remaining: remaining - FIXED_TIMESTEP,
+            },
+            EnemyState::Webbed { .. } => {
+                evw_enemy.send(EnemyEvent::BrokeFree(entity));
+                EnemyState::Patrol
+            }
+            EnemyState::Attack { target, remaining } if remaining > FIXED_TIMESTEP => {
+                EnemyState::Attack {
+                    target,
+                    remaining: remaining - FIXED_TIMESTEP,
+                }
+            }
+            EnemyState::Attack { target, .. } => EnemyState::Chase(target),
+            EnemyState::Patrol | EnemyState::Chase(_) => match closest_player() {
+                None => EnemyState::Patrol,
+                Some((player, player_translation))
+                    if player_translation.distance(translation) <= enemy.attack_range
+                        && enemy.attack_cooldown_counter <= 0.0 =>
+                {
+                    // Hop toward the player so crawlers don't just scrape along the ground
+                    let direction =
+                        (player_translation - translation).normalize_or_zero() + Vec2::Y;
+                    impulse.impulse = enemy.attack_impulse * direction.normalize_or_zero();
+                    enemy.attack_cooldown_counter = enemy.attack_cooldown;
+                    evw_enemy.send(EnemyEvent::Attacked(entity, player));
+                    EnemyState::Attack {
+                        target: player,
+                        remaining: ATTACK_TIME,
+                    }
+                }
+                Some((player, _)) => {
+                    (*state == EnemyState::Patrol).then(|| {
+                        evw_enemy.send(EnemyEvent::Spotted(entity, player));
+                    });
+                    EnemyState::Chase(player)
+                }
+            },
+        };
+        if *state != next_state {
+            *state = next_state;
+        }
+    });
+}
+
+/// Steer enemies toward their next patrol point or the player they chase. Crawlers only steer
+/// horizontally and leave the rest to gravity, flyers move straight at their target.
+pub fn move_enemies(
+    mut q_enemy: Query<(
+        &Enemy,
+        &EnemyState,
+        &GlobalTransform,
+        &mut PatrolPath,
+        &mut Velocity,
+    )>,
+    q_player: Query<&GlobalTransform, With<Player>>,
+) {
+    q_enemy.for_each_mut(|(enemy, state, transform, mut patrol, mut velocity)| {
+        let translation = transform.translation().truncate();
+        let origin = *patrol.origin.get_or_insert(translation);
+        let chased = match *state {
+            EnemyState::Chase(player) => q_player.get(player).ok(),
+            EnemyState::Patrol => None,
+            // Stuck enemies stay put, lunges keep their momentum
+            EnemyState::Webbed { .. } => {
+                velocity.linvel = match enemy.kind {
+                    EnemyKind::Crawler => Vec2::new(0.0, velocity.linvel.y),
+                    EnemyKind::Flyer => Vec2::ZERO,
+                };
+                return;
+            }
+            EnemyState::Attack { .. } => return,
+        };
+
+        let (target, speed) = match chased {
+            Some(player_transform) => {
+                (player_transform.translation().truncate(), enemy.chase_speed)
+            }
+            None => {
+                let Some(point) = patrol.points.get(patrol.next).map(|point| origin + *point)
+                else {
+                    return;
+                };
+                let remaining = match enemy.kind {
+                    EnemyKind::Crawler => (point.x - translation.x).abs(),
+                    EnemyKind::Flyer => point.distance(translation),
+                };
+                if remaining <= ARRIVE_DISTANCE {
+                    patrol.next = (patrol.next + 1) % patrol.points.len();
+                }
+                (point, enemy.patrol_speed)
+            }
+        };
+
+        let to_target = target - translation;
+        match enemy.kind {
+            EnemyKind::Crawler => {
+                velocity.linvel.x = (to_target.x.abs() > ARRIVE_DISTANCE)
+                    .then(|| to_target.x.signum() * speed)
+                    .unwrap_or(0.0);
+            }
+            EnemyKind::Flyer => {
+                velocity.linvel = (to_target.length() > ARRIVE_DISTANCE)
+                    .then(|| to_target.normalize() * speed)
+                    .unwrap_or(Vec2::ZERO);
+            }
+        }
+    });
+}
@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::Velocity;
+use sprite_animation::prelude::{
+    match_node::MatchNode,
+    play_node::{PlayNode, SpriteAnimation},
+    *,
+};
+
+use crate::{data::enemy::EnemyState, utils::state_helper::StateExtend, GameState};
+
+use super::EnemyAnimState;
+
+const FPS: usize = 12;
+
+fn create_walking_anim() -> AnimNode<EnemyAnimState> {
+    AnimNode::PlayNode(PlayNode::new(
+        1.,
+        true,
+        true,
+        SpriteAnimation::new_range(FPS, 9, 14),
+    ))
+}
+fn create_chasing_anim() -> AnimNode<EnemyAnimState> {
+    AnimNode::PlayNode(PlayNode::new(
+        2.,
+        true,
+        true,
+        SpriteAnimation::new_range(FPS, 9, 14),
+    ))
+}
+fn create_attacking_anim() -> AnimNode<EnemyAnimState> {
+    AnimNode::PlayNode(PlayNode::new(
+        1.,
+        false,
+        true,
+        SpriteAnimation::new(FPS, &[18, 21, 22]),
+    ))
+}
+fn create_webbed_anim() -> AnimNode<EnemyAnimState> {
+    AnimNode::PlayNode(PlayNode::new(
+        0.5,
+        true,
+        true,
+        SpriteAnimation::new_range(FPS, 45, 47),
+    ))
+}
+impl ToString for EnemyAnimState {
+    fn to_string(&self) -> String {
+        String::from(match self {
+            EnemyAnimState::Walking => "EnemyAnimState::Walking",
+            EnemyAnimState::Chasing => "EnemyAnimState::Chasing",
+            EnemyAnimState::Attacking => "EnemyAnimState::Attacking",
+            EnemyAnimState::Webbed => "EnemyAnimState::Webbed",
+        })
+    }
+}
+impl AnimState for EnemyAnimState {}
+
+#[derive(Resource, Default)]
+pub struct EnemyAnimTree(AnimTree<EnemyAnimState>);
+impl AnimTreeWrap<EnemyAnimState> for EnemyAnimTree {
+    fn get(&self) -> &AnimTree<EnemyAnimState> {
+        &self.0
+    }
+    fn get_mut(&mut self) -> &mut AnimTree<EnemyAnimState> {
+        &mut self.0
+    }
+}
+
+pub struct EnemyAnimationPlugin {
+    run_in: Option<GameState>,
+}
+impl EnemyAnimationPlugin {
+    pub fn new(state: Option<GameState>) -> Self {
+        Self { run_in: state }
+    }
+}
+
+impl Plugin for EnemyAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(build_anim_tree())
+            .add_plugin(AnimPlugin::<EnemyAnimTree, EnemyAnimState, GameState>::new(
+                self.run_in,
+            ))
+            .add_system_run_if(self.run_in, update_animation);
+    }
+}
+
+fn build_anim_tree() -> EnemyAnimTree {
+    let mut start_node: MatchNode<EnemyAnimState> = MatchNode::new();
+    let walking_node = create_walking_anim();
+    let chasing_node = create_chasing_anim();
+    let attacking_node = create_attacking_anim();
+    let webbed_node = create_webbed_anim();
+
+    start_node
+        .insert(EnemyAnimState::Walking, walking_node.get_id())
+        .insert(EnemyAnimState::Chasing, chasing_node.get_id())
+        .insert(EnemyAnimState::Attacking, attacking_node.get_id())
+        .insert(EnemyAnimState::Webbed, webbed_node.get_id());
+
+    let mut tree = EnemyAnimTree(AnimTree::<EnemyAnimState>::new(AnimNode::MatchNode(
+        start_node,
+    )));
+    tree.get_mut()
+        .insert_unwrap(walking_node)
+        .insert_unwrap(chasing_node)
+        .insert_unwrap(attacking_node)
+        .insert_unwrap(webbed_node);
+
+    tree
+}
+
+/// The animation follows the AI state, the sprite faces where the enemy is heading
+fn update_animation(
+    mut q: Query<(
+        &EnemyState,
+        &Velocity,
+        &mut AnimData<EnemyAnimState>,
+        &mut TextureAtlasSprite,
+    )>,
+) {
+    q.for_each_mut(|(state, velocity, mut anim_data, mut sprite)| {
+        let anim_state = match state {
+            EnemyState::Patrol => EnemyAnimState::Walking,
+            EnemyState::Chase(_) => EnemyAnimState::Chasing,
+            EnemyState::Attack { .. } => EnemyAnimState::Attacking,
+            EnemyState::Webbed { .. } => EnemyAnimState::Webbed,
+        };
+        anim_data.set_state(anim_state);
+
+        (velocity.linvel.x != 0.0).then(|| {
+            sprite.flip_x = velocity.linvel.x.gt(&0.0);
+        });
+    });
+}
@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::data::{
+    enemy::{Enemy, EnemyState},
+    web::{Web, WebHead},
+};
+
+use super::EnemyEvent;
+
+/// Flying web heads hitting an enemy leave it stuck for [`Enemy::webbed_duration`]. The web
+/// itself doesn't stick, it's despawned like on any other surface it can't hold on to.
+pub fn web_enemies(
+    mut q_enemy: Query<(&Enemy, &mut EnemyState)>,
+    q_web_head: Query<&Parent, With<WebHead>>,
+    q_web: Query<&Web>,
+    mut evr_collisions: EventReader<CollisionEvent>,
+    mut evw_enemy: EventWriter<EnemyEvent>,
+) {
+    for collision in evr_collisions.iter() {
+        let CollisionEvent::Started(entity_one, entity_two, _) = collision else {
+            continue;
+        };
+        let flying_web = |entity: Entity| {
+            q_web_head
+                .get(entity)
+                .ok()
+                .and_then(|parent| q_web.get(parent.get()).ok())
+                .map_or(false, |web| !web.attached)
+        };
+        let Some(enemy_entity) = flying_web(*entity_one)
+            .then_some(*entity_two)
+            .or_else(|| flying_web(*entity_two).then_some(*entity_one))
+        else {
+            continue;
+        };
+        let Ok((enemy, mut state)) = q_enemy.get_mut(enemy_entity) else {
+            continue;
+        };
+
+        if !matches!(*state, EnemyState::Webbed { .. }) {
+            evw_enemy.send(EnemyEvent::Webbed(enemy_entity));
+        }
+        *state = EnemyState::Webbed {
+            remaining: enemy.webbed_duration,
+        };
+    }
+}
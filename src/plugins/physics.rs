@@ -1,28 +1,136 @@
-use bevy::prelude::*;
+use std::time::Duration;
+
+use bevy::{ecs::event::Event, prelude::*};
+use bevy::transform::transform_propagate_system;
 use bevy_rapier2d::plugin::{NoUserData, RapierPhysicsPlugin};
-use bevy_rapier2d::prelude::{PhysicsStages, RapierConfiguration};
+use bevy_rapier2d::prelude::{PhysicsStages, RapierConfiguration, TimestepMode};
+use iyes_loopless::prelude::*;
 
+mod interpolation;
 mod pre_physics;
 
-#[derive(StageLabel)]
-enum PrePhysicsStages {
+/// Label of the fixed timestep stage running physics and gameplay
+pub const FIXED_UPDATE: &str = "fixed_update";
+/// Seconds simulated by each tick of [`FIXED_UPDATE`], gameplay systems in it use this instead
+/// of the frame time so a run plays out the same at any frame rate
+pub const FIXED_TIMESTEP: f32 = 1.0 / 60.0;
+
+/// Substages of [`FIXED_UPDATE`], run in this order every tick
+#[derive(Clone, Copy, Debug)]
+pub enum FixedUpdateStage {
+    RestoreTransforms,
+    /// Push transforms changed by gameplay to rapier
+    PropagateTransforms,
+    SyncBackend,
+    StepSimulation,
+    Writeback,
+    /// Make gameplay see the positions rapier just wrote back
+    PostPhysics,
+    /// Gameplay, split by plugin so systems of different plugins never race each other. Systems
+    /// in each of these run one after the other, see
+    /// [`crate::utils::state_helper::StateExtend::add_fixed_chain_run_if`]
+    PlayerMovement,
+    Webs,
+    World,
+    Enemies,
+    Damage,
     ResolveComplexForces,
 }
 
+impl FixedUpdateStage {
+    const COUNT: usize = Self::ResolveComplexForces as usize + 1;
+}
+
+pub trait FixedEventExt {
+    /// Like [`App::add_event`] but events are kept for two ticks instead of two frames, so
+    /// systems running every tick don't miss them when frames are faster than ticks
+    fn add_fixed_event<T: Event>(&mut self) -> &mut Self;
+}
+
+impl FixedEventExt for App {
+    fn add_fixed_event<T: Event>(&mut self) -> &mut Self {
+        self.init_resource::<Events<T>>().add_fixed_timestep_system(
+            FIXED_UPDATE,
+            FixedUpdateStage::RestoreTransforms as usize,
+            Events::<T>::update_system,
+        )
+    }
+}
+
 pub struct PhysicsPlugin;
 
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(8.0))
-            .add_startup_system(setup_physics)
-            .add_stage_before(
-                PhysicsStages::SyncBackend,
-                PrePhysicsStages::ResolveComplexForces,
-                SystemStage::parallel().with_system(pre_physics::compute_complex_external_forces),
-            );
+        app.add_plugin(
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(8.0)
+                .with_default_system_setup(false),
+        )
+        .add_startup_system(setup_physics)
+        .add_fixed_timestep(Duration::from_secs_f32(FIXED_TIMESTEP), FIXED_UPDATE);
+        (1..FixedUpdateStage::COUNT).for_each(|_| {
+            app.add_fixed_timestep_child_stage(FIXED_UPDATE);
+        });
+
+        app.add_fixed_timestep_system(
+            FIXED_UPDATE,
+            FixedUpdateStage::RestoreTransforms as usize,
+            interpolation::restore_physics_transforms,
+        )
+        .add_fixed_timestep_system(
+            FIXED_UPDATE,
+            FixedUpdateStage::PropagateTransforms as usize,
+            transform_propagate_system,
+        )
+        .add_fixed_timestep_system_set(
+            FIXED_UPDATE,
+            FixedUpdateStage::SyncBackend as usize,
+            RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::SyncBackend),
+        )
+        .add_fixed_timestep_system_set(
+            FIXED_UPDATE,
+            FixedUpdateStage::StepSimulation as usize,
+            RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::StepSimulation),
+        )
+        .add_fixed_timestep_system_set(
+            FIXED_UPDATE,
+            FixedUpdateStage::Writeback as usize,
+            RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::Writeback),
+        )
+        .add_fixed_timestep_system(
+            FIXED_UPDATE,
+            FixedUpdateStage::PostPhysics as usize,
+            transform_propagate_system,
+        )
+        .add_fixed_timestep_system(
+            FIXED_UPDATE,
+            FixedUpdateStage::ResolveComplexForces as usize,
+            pre_physics::compute_complex_external_forces,
+        )
+        .add_fixed_timestep_system(
+            FIXED_UPDATE,
+            FixedUpdateStage::ResolveComplexForces as usize,
+            interpolation::record_physics_transforms,
+        )
+        // Removals have to be caught every frame, ticks can be skipped
+        .add_stage_before(
+            CoreStage::Last,
+            PhysicsStages::DetectDespawn,
+            SystemStage::parallel().with_system_set(RapierPhysicsPlugin::<NoUserData>::get_systems(
+                PhysicsStages::DetectDespawn,
+            )),
+        )
+        .add_system_to_stage(
+            CoreStage::PostUpdate,
+            interpolation::interpolate_physics_transforms
+                .before(bevy::transform::TransformSystem::TransformPropagate),
+        );
     }
 }
 
 pub fn setup_physics(mut config: ResMut<RapierConfiguration>) {
     config.gravity = Vec2::new(0.0, -300.0);
+    config.timestep_mode = TimestepMode::Fixed {
+        dt: FIXED_TIMESTEP,
+        substeps: 1,
+    };
 }
@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::FixedTimesteps;
+
+use crate::data::physics::PhysicsInterpolation;
+
+use super::FIXED_UPDATE;
+
+/// Put back the translation of the last tick so rapier doesn't see the blended one as a teleport.
+/// A translation changed outside of the ticks since then is kept and restarts the blending.
+pub fn restore_physics_transforms(
+    mut q_interpolated: Query<(&mut Transform, &mut PhysicsInterpolation)>,
+) {
+    q_interpolated.for_each_mut(|(mut transform, mut interpolation)| {
+        if !interpolation.started {
+            return;
+        }
+        if transform.translation == interpolation.rendered {
+            transform.translation = interpolation.current;
+        } else {
+            interpolation.previous = transform.translation;
+            interpolation.current = transform.translation;
+        }
+    });
+}
+
+pub fn record_physics_transforms(
+    mut q_interpolated: Query<(&Transform, &mut PhysicsInterpolation)>,
+) {
+    q_interpolated.for_each_mut(|(transform, mut interpolation)| {
+        interpolation.previous = interpolation
+            .started
+            .then_some(interpolation.current)
+            .unwrap_or(transform.translation);
+        interpolation.current = transform.translation;
+        interpolation.rendered = transform.translation;
+        interpolation.started = true;
+    });
+}
+
+/// Blend between the last two ticks by how far the frame is into the next one
+pub fn interpolate_physics_transforms(
+    timesteps: Res<FixedTimesteps>,
+    mut q_interpolated: Query<(&mut Transform, &mut PhysicsInterpolation)>,
+) {
+    let Some(overstep) = timesteps
+        .get(FIXED_UPDATE)
+        .map(|timestep| timestep.overstep_percentage() as f32)
+    else {
+        return;
+    };
+    q_interpolated.for_each_mut(|(mut transform, mut interpolation)| {
+        if !interpolation.started || transform.translation != interpolation.rendered {
+            return;
+        }
+        let rendered = interpolation
+            .previous
+            .lerp(interpolation.current, overstep.clamp(0.0, 1.0));
+        transform.translation = rendered;
+        interpolation.rendered = rendered;
+    });
+}
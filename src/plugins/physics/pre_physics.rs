@@ -3,10 +3,13 @@ use bevy_rapier2d::prelude::*;
 
 use crate::data::physics::ComplexExternalForce;
 
+use super::FIXED_TIMESTEP;
+
 pub fn compute_complex_external_forces(
-    mut q_cef: Query<(&mut ExternalForce, &ComplexExternalForce)>,
+    mut q_cef: Query<(&mut ExternalForce, &mut ComplexExternalForce)>,
 ) {
-    q_cef.for_each_mut(|(mut ef, cef)| {
+    q_cef.for_each_mut(|(mut ef, mut cef)| {
         ef.force = cef.composite_force();
+        cef.decay(FIXED_TIMESTEP);
     });
 }
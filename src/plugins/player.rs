@@ -1,19 +1,40 @@
+mod aim;
 mod animation;
+mod crawl;
+mod input;
 mod jump;
 pub mod lifecycle;
 mod movement;
 mod shoot_web;
+mod wall;
+mod web_bridge;
+mod web_grab;
+mod web_rules;
+mod web_tether;
 
 use bevy::prelude::*;
 
-use crate::{utils::state_helper::StateExtend, GameState};
+use crate::{
+    data::player::{PlayerAbilities, WebUpgrade},
+    plugins::physics::{FixedEventExt, FixedUpdateStage},
+    utils::state_helper::StateExtend,
+    GameState,
+};
 
 use self::{
+    aim::{setup_aim_preview_assets, spawn_aim_previews, update_aim_preview, AimPreviewAssets},
     animation::PlayerAnimationPlugin,
-    jump::{check_if_grounded, check_if_head_bump, handle_jump},
+    crawl::handle_crawl,
+    input::{clear_player_input_presses, read_player_input},
+    jump::{check_if_grounded, check_if_head_bump, handle_jump, update_coyote_time},
     lifecycle::*,
     movement::{apply_accel_when_land, handle_movement},
     shoot_web::*,
+    wall::{check_if_on_wall, handle_wall_jump, handle_wall_slide},
+    web_bridge::build_web_bridge,
+    web_grab::{follow_moving_platforms, pull_web_grabbed_objects},
+    web_rules::{collect_web_upgrades, snap_webs, update_web_cooldown},
+    web_tether::pull_tethered_players,
 };
 
 #[derive(Eq, Hash, PartialEq, Default, Clone, Copy, Debug)]
@@ -25,6 +46,8 @@ pub enum PlayerAnimState {
     MidAir,
     Jumping,
     Landing,
+    WallSliding,
+    WallJumping,
     Hurt,
     None,
 }
@@ -35,22 +58,92 @@ pub enum PlayerEvent {
     Jumped(Entity),
     Grounded(Entity),
     Died(Entity),
-    // Hurt(Entity),
+    Hurt(Entity),
     // Attacks(Entity),
     /// Axis [f32], Player [Entity]
     Moving(f32, Entity),
     Standing(Entity),
-    ShotWeb,
-    Collided(Entity),
+    WallSliding(Entity),
+    WallReleased(Entity),
+    WallJumped(Entity),
+    StartedCrawling(Entity),
+    StoppedCrawling(Entity),
+    ShotWeb(Entity),
+    /// Player [Entity], the entity it ran into [Entity]
+    Collided(Entity, Entity),
+}
+impl PlayerEvent {
+    /// The player the event happened to
+    pub fn player(&self) -> Entity {
+        match self {
+            PlayerEvent::Airborne(player)
+            | PlayerEvent::Jumped(player)
+            | PlayerEvent::Grounded(player)
+            | PlayerEvent::Died(player)
+            | PlayerEvent::Hurt(player)
+            | PlayerEvent::Moving(_, player)
+            | PlayerEvent::Standing(player)
+            | PlayerEvent::WallSliding(player)
+            | PlayerEvent::WallReleased(player)
+            | PlayerEvent::WallJumped(player)
+            | PlayerEvent::StartedCrawling(player)
+            | PlayerEvent::StoppedCrawling(player)
+            | PlayerEvent::ShotWeb(player)
+            | PlayerEvent::Collided(player, _) => *player,
+        }
+    }
 }
 
-#[derive(Resource, Debug)]
+/// Sent when webs catch or let go of [`crate::data::tilemap::WebGrabbable`] objects, level logic
+/// listens to these to open doors and such
+#[derive(Debug)]
+pub enum WebGrabEvent {
+    Grabbed { web: Entity, object: Entity },
+    Released { web: Entity, object: Entity },
+    SwitchFlipped { switch: Entity, is_on: bool },
+}
+
+/// Web cooldown, durability and upgrade changes for the HUD and save data to follow
+#[derive(Debug)]
+pub enum WebEvent {
+    CooldownStarted { player: Entity, duration: f32 },
+    CooldownFinished(Entity),
+    /// The web was attached for too long or pulled too hard
+    Snapped(Entity),
+    UpgradeUnlocked(Entity, WebUpgrade),
+    /// The web stuck to another player, tying the two together
+    Tethered {
+        web: Entity,
+        player: Entity,
+    },
+}
+
+/// Where a player aims their webs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AimControl {
+    /// Toward the mouse cursor, left click shoots and right click removes the newest web
+    Mouse,
+    /// Toward the held direction keys, straight up when none are held
+    Keys { shoot: KeyCode, release: KeyCode },
+}
+
+/// Keys of a local player. Players without it get their [`PlayerInput`] from elsewhere, a replay
+/// or a test.
+#[derive(Component, Debug)]
 pub struct PlayerControl {
     // attack: KeyCode,
     jump: KeyCode,
 
     left: KeyCode,
     right: KeyCode,
+    up: KeyCode,
+    down: KeyCode,
+    crawl: KeyCode,
+    /// Default to the up and down keys, crawling takes them over while the player crawls
+    reel_in: KeyCode,
+    reel_out: KeyCode,
+    bridge: KeyCode,
+    aim: AimControl,
 }
 impl Default for PlayerControl {
     fn default() -> Self {
@@ -59,51 +152,290 @@ impl Default for PlayerControl {
             jump: KeyCode::Space,
             left: KeyCode::A,
             right: KeyCode::D,
+            up: KeyCode::W,
+            down: KeyCode::S,
+            crawl: KeyCode::LShift,
+            reel_in: KeyCode::W,
+            reel_out: KeyCode::S,
+            bridge: KeyCode::E,
+            aim: AimControl::Mouse,
+        }
+    }
+}
+impl PlayerControl {
+    /// The first player plays with WASD and the mouse, the second one with the arrow keys
+    pub fn for_player(index: usize) -> Self {
+        match index {
+            0 => Self::default(),
+            _ => Self {
+                jump: KeyCode::RShift,
+                left: KeyCode::Left,
+                right: KeyCode::Right,
+                up: KeyCode::Up,
+                down: KeyCode::Down,
+                crawl: KeyCode::RControl,
+                reel_in: KeyCode::Up,
+                reel_out: KeyCode::Down,
+                bridge: KeyCode::Slash,
+                aim: AimControl::Keys {
+                    shoot: KeyCode::Period,
+                    release: KeyCode::Comma,
+                },
+            },
+        }
+    }
+}
+
+/// Input of one player, sampled every frame for the fixed gameplay tick. Presses are kept until a
+/// tick consumes them so none are lost or repeated when a frame runs zero or several ticks.
+#[derive(Component, Default, Debug, Clone)]
+pub struct PlayerInput {
+    pub left: bool,
+    pub right: bool,
+    pub up: bool,
+    pub down: bool,
+    pub jump: bool,
+    pub crawl: bool,
+    pub reel_in: bool,
+    pub reel_out: bool,
+
+    pub jump_pressed: bool,
+    pub bridge_pressed: bool,
+    pub shoot_pressed: bool,
+    pub release_pressed: bool,
+    /// Where the player aims in world space, the mouse cursor or a point in the held direction
+    pub cursor: Option<Vec2>,
+}
+
+impl PlayerInput {
+    /// -1.0 for left, 1.0 for right and 0.0 for neither or both
+    pub fn horizontal(&self) -> f32 {
+        self.right.then_some(1.0).unwrap_or(0.0) - self.left.then_some(1.0).unwrap_or(0.0)
+    }
+
+    pub fn vertical(&self) -> f32 {
+        self.up.then_some(1.0).unwrap_or(0.0) - self.down.then_some(1.0).unwrap_or(0.0)
+    }
+}
+
+/// Decide whether the player spawns with [`crate::data::player::PlayerHealth`] or dies on the first hit
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    /// Any trap kills the player instantly
+    Classic,
+    #[default]
+    Health,
+}
+
+/// How an attached web holds the player
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebPhysics {
+    /// Pull force growing with how far the player stretched past the initial web length
+    Spring,
+    /// Rapier rope joint between the player and the web head
+    #[default]
+    Rope,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct WebSettings {
+    pub physics: WebPhysics,
+    /// Webs landing further away from the player than this are despawned, reeling out stops
+    /// at this length. Scaled by [`PlayerAbilities::range_scale`].
+    pub max_rope_length: f32,
+    /// Reeling in stops at this length
+    pub min_rope_length: f32,
+    /// Length change per second while reeling in or out
+    pub reel_speed: f32,
+    /// Web heads flying further than this from the player are despawned
+    pub max_range: f32,
+    /// Snap the shot toward a web stickable surface close to the cursor direction
+    pub aim_assist: bool,
+    /// Half angle of the assist cone in radians
+    pub aim_assist_angle: f32,
+    /// Webs alive at once, bridges included. Shooting another one drops the oldest
+    pub max_webs: usize,
+    pub bridge_thickness: f32,
+    /// Restitution of web bridges, 0 is a plain walkable platform
+    pub bridge_bounciness: f32,
+    /// Minimum time between two shots, `None` shoots without cooldown
+    pub shoot_cooldown: Option<f32>,
+    /// Attached webs snap after this many seconds, `None` keeps them forever
+    pub max_attached_time: Option<f32>,
+    /// Attached webs snap when the tension on them goes past this, see [`web_rules::snap_webs`]
+    pub snap_tension: Option<f32>,
+}
+impl Default for WebSettings {
+    fn default() -> Self {
+        Self {
+            physics: Default::default(),
+            max_rope_length: 120.0,
+            min_rope_length: 8.0,
+            reel_speed: 40.0,
+            max_range: 120.0,
+            aim_assist: true,
+            aim_assist_angle: 0.2,
+            max_webs: 2,
+            bridge_thickness: 1.0,
+            bridge_bounciness: 0.6,
+            shoot_cooldown: None,
+            max_attached_time: None,
+            snap_tension: None,
         }
     }
 }
+impl WebSettings {
+    /// Longest rope a player with these abilities holds, the long range upgrade scales it along
+    /// with the range
+    pub fn rope_length_limit(&self, abilities: &PlayerAbilities) -> f32 {
+        self.max_rope_length * abilities.range_scale()
+    }
+
+    /// How far the player can aim a web that holds, neither past the range nor past the rope
+    pub fn reach(&self, abilities: &PlayerAbilities) -> f32 {
+        self.max_range.min(self.max_rope_length) * abilities.range_scale()
+    }
+}
 
 #[derive(Resource, Debug)]
 pub struct PlayerSwingDirection(f32);
 
+/// Local players are limited by the key layouts of [`PlayerControl::for_player`]
+pub const MAX_PLAYERS: usize = 2;
+
+/// Players spawned when the game starts
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerCount(pub usize);
+
+/// Labels of the player systems running every tick, in the order they run
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum PlayerSystem {
+    CheckIfGrounded,
+    CheckIfOnWall,
+    CheckIfHeadBump,
+    UpdateCoyoteTime,
+    HandleJump,
+    HandleWallJump,
+    HandleCrawl,
+    HandleWallSlide,
+    HandleMovement,
+    ApplyAccelWhenLand,
+    HandleShootWebInput,
+    ShootWeb,
+    LimitWebRange,
+    HandleWebHeadCollision,
+    HandleWebReel,
+    BuildWebBridge,
+    PullWebGrabbedObjects,
+    PullTetheredPlayers,
+    FollowMovingPlatforms,
+    UpdateWebCooldown,
+    SnapWebs,
+    CollectWebUpgrades,
+    WrapWebAroundTerrain,
+    UpdateWebString,
+    DespawnWebOnPlayerDeath,
+    DespawnWeb,
+    PlayerCollision,
+    DamagePlayer,
+    UpdateInvulnerability,
+    RespawnPlayerOnDeath,
+}
+
 #[derive(Resource)]
 pub struct PlayerPlugin {
     run_in: Option<GameState>,
+    player_count: usize,
 }
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.insert_resource(PlayerControl::default())
+        app.insert_resource(PlayerCount(self.player_count))
+            .insert_resource(GameMode::default())
+            .insert_resource(WebSettings::default())
             .insert_resource(WebTexture::default())
-            .add_event::<PlayerEvent>()
-            .add_event::<DespawnWebEvent>()
+            .insert_resource(WebCounter::default())
+            .insert_resource(AimPreviewAssets::default())
+            .add_fixed_event::<PlayerEvent>()
+            .add_fixed_event::<DespawnWebEvent>()
+            .add_fixed_event::<WebGrabEvent>()
+            .add_fixed_event::<WebEvent>()
             .add_plugin(PlayerAnimationPlugin::new(self.run_in))
             .add_startup_system_if_state(self.run_in, setup_web_texture)
             .add_startup_system_if_state(self.run_in, spawn_player_at_start)
-            // movements
-            .add_system_run_if(self.run_in, handle_jump)
-            .add_system_run_if(self.run_in, check_if_grounded)
-            .add_system_run_if(self.run_in, handle_movement)
-            .add_system_run_if(self.run_in, check_if_head_bump)
-            .add_system_run_if(self.run_in, apply_accel_when_land)
-            // shoot web
-            .add_system_run_if(self.run_in, handle_shoot_web_input)
-            .add_system_run_if(self.run_in, shoot_web)
-            .add_system_run_if(self.run_in, handle_web_head_collision)
-            .add_system_run_if(self.run_in, update_web_string_and_pull_force)
-            .add_system_run_if(self.run_in, despawn_web)
-            .add_system_run_if(self.run_in, despawn_web_on_player_death)
-            // collision
-            .add_system_run_if(self.run_in, player_collision)
-            // death
-            .add_system_run_if(self.run_in, kill_player)
-            .add_system_run_if(self.run_in, respawn_player_on_death);
+            .add_startup_system_if_state(self.run_in, setup_aim_preview_assets)
+            // input and visuals, every frame
+            .add_system_run_if(self.run_in, read_player_input)
+            .add_system_run_if(self.run_in, spawn_aim_previews)
+            .add_system_run_if(self.run_in, update_aim_preview)
+            // presses are used up once every gameplay system saw them
+            .add_fixed_system_run_if(
+                self.run_in,
+                FixedUpdateStage::ResolveComplexForces,
+                clear_player_input_presses,
+            );
+
+        // The jump buffer is filled by handle_jump, then wall jumps and crawl jumps use it up
+        app.add_fixed_chain_run_if(self.run_in, FixedUpdateStage::PlayerMovement)
+            .then(PlayerSystem::CheckIfGrounded, check_if_grounded)
+            .then(PlayerSystem::CheckIfOnWall, check_if_on_wall)
+            .then(PlayerSystem::CheckIfHeadBump, check_if_head_bump)
+            .then(PlayerSystem::UpdateCoyoteTime, update_coyote_time)
+            .then(PlayerSystem::HandleJump, handle_jump)
+            .then(PlayerSystem::HandleWallJump, handle_wall_jump)
+            .then(PlayerSystem::HandleCrawl, handle_crawl)
+            .then(PlayerSystem::HandleWallSlide, handle_wall_slide)
+            .then(PlayerSystem::HandleMovement, handle_movement)
+            .then(PlayerSystem::ApplyAccelWhenLand, apply_accel_when_land);
+        app.add_fixed_chain_run_if(self.run_in, FixedUpdateStage::Webs)
+            .then(PlayerSystem::HandleShootWebInput, handle_shoot_web_input)
+            .then(PlayerSystem::ShootWeb, shoot_web)
+            .then(PlayerSystem::LimitWebRange, limit_web_range)
+            .then(
+                PlayerSystem::HandleWebHeadCollision,
+                handle_web_head_collision,
+            )
+            .then(PlayerSystem::HandleWebReel, handle_web_reel)
+            .then(PlayerSystem::BuildWebBridge, build_web_bridge)
+            .then(
+                PlayerSystem::PullWebGrabbedObjects,
+                pull_web_grabbed_objects,
+            )
+            .then(PlayerSystem::PullTetheredPlayers, pull_tethered_players)
+            .then(PlayerSystem::FollowMovingPlatforms, follow_moving_platforms)
+            .then(PlayerSystem::UpdateWebCooldown, update_web_cooldown)
+            .then(PlayerSystem::SnapWebs, snap_webs)
+            .then(PlayerSystem::CollectWebUpgrades, collect_web_upgrades)
+            .then(PlayerSystem::WrapWebAroundTerrain, wrap_web_around_terrain)
+            .then(
+                PlayerSystem::UpdateWebString,
+                update_web_string_and_pull_force,
+            )
+            .then(
+                PlayerSystem::DespawnWebOnPlayerDeath,
+                despawn_web_on_player_death,
+            )
+            .then(PlayerSystem::DespawnWeb, despawn_web);
+        app.add_fixed_chain_run_if(self.run_in, FixedUpdateStage::Damage)
+            .then(PlayerSystem::PlayerCollision, player_collision)
+            .then(PlayerSystem::DamagePlayer, damage_player)
+            .then(PlayerSystem::UpdateInvulnerability, update_invulnerability)
+            .then(PlayerSystem::RespawnPlayerOnDeath, respawn_player_on_death);
 
         // Add test in the test plugin for easy clean up
     }
 }
 impl PlayerPlugin {
     pub fn new(run_in: Option<GameState>) -> Self {
-        Self { run_in }
+        Self {
+            run_in,
+            player_count: 1,
+        }
+    }
+
+    /// Local co-op, up to [`MAX_PLAYERS`]
+    pub fn with_player_count(mut self, player_count: usize) -> Self {
+        self.player_count = player_count.clamp(1, MAX_PLAYERS);
+        self
     }
 }
 
@@ -111,11 +443,17 @@ fn spawn_player_at_start(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    game_mode: Res<GameMode>,
+    player_count: Res<PlayerCount>,
 ) {
-    spawn_player(
-        &mut commands,
-        Transform::from_translation(translation_in_level(0)),
-        asset_server.as_ref(),
-        texture_atlases.as_mut(),
-    );
+    (0..player_count.0).for_each(|index| {
+        spawn_player(
+            &mut commands,
+            index,
+            Transform::from_translation(translation_in_level(0)),
+            asset_server.as_ref(),
+            texture_atlases.as_mut(),
+            *game_mode,
+        );
+    });
 }
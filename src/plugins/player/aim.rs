@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use bevy::sprite::MaterialMesh2dBundle;
+use bevy_rapier2d::prelude::*;
+
+use crate::data::{
+    physics::{CollisionGroupsFilter, GameCollisionGroups},
+    player::{Player, PlayerAbilities},
+    tilemap::WebStickable,
+    web::WebAimPreview,
+};
+
+use super::{lifecycle::player_of_collider, PlayerInput, WebSettings};
+
+const AIM_PREVIEW_Z: f32 = 99.0;
+const AIM_PREVIEW_WIDTH: f32 = 0.3;
+/// Rays cast on each side of the cursor direction when looking for a surface to snap to
+const AIM_ASSIST_RAYS: usize = 6;
+
+#[derive(Resource, Default)]
+pub struct AimPreviewAssets {
+    mesh: Handle<Mesh>,
+    hit: Handle<ColorMaterial>,
+    miss: Handle<ColorMaterial>,
+}
+
+/// Where a web shot in the current aim direction would go
+pub struct Aim {
+    pub direction: Vec2,
+    /// Where the shot stops, on a stickable surface or not, `None` when nothing is in range
+    pub hit: Option<Vec2>,
+    pub will_stick: bool,
+}
+
+/// Raycast the web path from the player toward the cursor. With aim assist on, the closest
+/// direction inside the assist cone that reaches a surface the web sticks to is used instead.
+pub fn resolve_aim(
+    shooter: Entity,
+    from: Vec2,
+    toward: Vec2,
+    max_range: f32,
+    web_settings: &WebSettings,
+    rapier_context: &RapierContext,
+    sticks_to: impl Fn(Entity) -> bool,
+) -> Option<Aim> {
+    let direction = (toward - from).try_normalize()?;
+    // Webs go through the body of the player shooting them
+    let filter = QueryFilter::new()
+        .exclude_sensors()
+        .exclude_rigid_body(shooter)
+        .groups(CollisionGroups::new(
+            GameCollisionGroups::WEB,
+            GameCollisionGroups::WEB.filter_group(),
+        ));
+    let cast = |direction: Vec2| {
+        let hit = rapier_context.cast_ray(from, direction, max_range, true, filter);
+        Aim {
+            direction,
+            hit: hit.map(|(_, toi)| from + direction * toi),
+            will_stick: hit.map_or(false, |(entity, _)| sticks_to(entity)),
+        }
+    };
+
+    let aim = cast(direction);
+    if aim.will_stick || !web_settings.aim_assist {
+        return Some(aim);
+    }
+
+    let step = web_settings.aim_assist_angle / AIM_ASSIST_RAYS as f32;
+    (1..=AIM_ASSIST_RAYS)
+        .flat_map(|i| [i as f32 * step, -(i as f32) * step])
+        .map(|angle| cast(Vec2::from_angle(angle).rotate(direction)))
+        .find(|assisted| assisted.will_stick)
+        .or(Some(aim))
+}
+
+pub fn setup_aim_preview_assets(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut preview_assets: ResMut<AimPreviewAssets>,
+) {
+    *preview_assets = AimPreviewAssets {
+        mesh: meshes.add(shape::Box::new(1.0, 1.0, 1.0).into()),
+        hit: materials.add(ColorMaterial::from(Color::rgba(0.6, 1.0, 0.6, 0.5))),
+        miss: materials.add(ColorMaterial::from(Color::rgba(1.0, 0.4, 0.4, 0.3))),
+    };
+}
+
+/// Give every new player an aim preview
+pub fn spawn_aim_previews(
+    q_player: Query<Entity, Added<Player>>,
+    preview_assets: Res<AimPreviewAssets>,
+    mut commands: Commands,
+) {
+    q_player.for_each(|player| {
+        commands
+            .spawn(MaterialMesh2dBundle {
+                mesh: preview_assets.mesh.clone().into(),
+                material: preview_assets.miss.clone(),
+                transform: Transform::from_scale(Vec3::ZERO),
+                ..default()
+            })
+            .insert(WebAimPreview { player })
+            .insert(Name::from("Web aim preview"));
+    });
+}
+
+pub fn update_aim_preview(
+    mut q_preview: Query<(
+        Entity,
+        &WebAimPreview,
+        &mut Transform,
+        &mut Handle<ColorMaterial>,
+    )>,
+    q_player: Query<(&GlobalTransform, &PlayerAbilities, &PlayerInput), With<Player>>,
+    q_web_stickable: Query<Entity, With<WebStickable>>,
+    q_parents: Query<&Parent>,
+    web_settings: Res<WebSettings>,
+    preview_assets: Res<AimPreviewAssets>,
+    rapier_context: Res<RapierContext>,
+    mut commands: Commands,
+) {
+    q_preview.for_each_mut(|(entity, preview, mut transform, mut material)| {
+        let Ok((player_transform, abilities, input)) = q_player.get(preview.player) else {
+            commands.entity(entity).despawn_recursive();
+            return;
+        };
+        let player_translation = player_transform.translation().truncate();
+        let max_range = web_settings.reach(abilities);
+
+        let aim = input.cursor.and_then(|cursor| {
+            resolve_aim(
+                preview.player,
+                player_translation,
+                cursor,
+                max_range,
+                &web_settings,
+                &rapier_context,
+                |entity| {
+                    q_web_stickable.contains(entity)
+                        || player_of_collider(&q_parents, |e| q_player.contains(e), entity)
+                            .is_some()
+                },
+            )
+        });
+        let Some(aim) = aim else {
+            transform.scale = Vec3::ZERO;
+            return;
+        };
+
+        let end = aim
+            .hit
+            .unwrap_or(player_translation + aim.direction * max_range);
+        transform.translation = ((player_translation + end) / 2.0).extend(AIM_PREVIEW_Z);
+        transform.rotation = Quat::from_rotation_z(-aim.direction.angle_between(Vec2::X));
+        transform.scale = Vec3::new(player_translation.distance(end), AIM_PREVIEW_WIDTH, 1.0);
+        *material = aim
+            .will_stick
+            .then(|| preview_assets.hit.clone())
+            .unwrap_or_else(|| preview_assets.miss.clone());
+    });
+}
@@ -61,6 +61,22 @@ fn create_land_anim() -> AnimNode<PlayerAnimState> {
         SpriteAnimation::new_range(FPS, 24, 26),
     ))
 }
+fn create_wall_slide_anim() -> AnimNode<PlayerAnimState> {
+    AnimNode::PlayNode(PlayNode::new(
+        1.,
+        true,
+        true,
+        SpriteAnimation::new(FPS, &[27]),
+    ))
+}
+fn create_hurt_anim() -> AnimNode<PlayerAnimState> {
+    AnimNode::PlayNode(PlayNode::new(
+        1.,
+        false,
+        true,
+        SpriteAnimation::new_range(FPS, 45, 47),
+    ))
+}
 impl ToString for PlayerAnimState {
     fn to_string(&self) -> String {
         String::from(match self {
@@ -68,6 +84,8 @@ impl ToString for PlayerAnimState {
             PlayerAnimState::Walking => "PlayerState::Walking",
             PlayerAnimState::MidAir => "PlayerState::MidAir",
             PlayerAnimState::Landing => "PlayerAnimState::Landing",
+            PlayerAnimState::WallSliding => "PlayerAnimState::WallSliding",
+            PlayerAnimState::WallJumping => "PlayerAnimState::WallJumping",
             PlayerAnimState::Jumping => "PlayerAnimState::Jumping",
             PlayerAnimState::Hurt => "PlayerAnimState::Hurt",
             PlayerAnimState::Standing => "PlayerAnimState::Standing",
@@ -113,6 +131,8 @@ fn build_anim_tree() -> PlayerAnimTree {
     let float_node = create_floating_anim();
     let land_anim_node = create_land_anim();
     let stand_node = create_standing_anim();
+    let hurt_node = create_hurt_anim();
+    let wall_slide_node = create_wall_slide_anim();
 
     let mut jump_node = AllNode::new();
     jump_node.nodes = vec![jump_anim_node.get_id(), float_node.get_id()];
@@ -128,7 +148,10 @@ fn build_anim_tree() -> PlayerAnimTree {
         .insert(PlayerAnimState::Jumping, jump_node.get_id())
         .insert(PlayerAnimState::MidAir, float_node.get_id())
         .insert(PlayerAnimState::Landing, land_node.get_id())
-        .insert(PlayerAnimState::Standing, stand_node.get_id());
+        .insert(PlayerAnimState::Standing, stand_node.get_id())
+        .insert(PlayerAnimState::WallSliding, wall_slide_node.get_id())
+        .insert(PlayerAnimState::WallJumping, jump_node.get_id())
+        .insert(PlayerAnimState::Hurt, hurt_node.get_id());
 
     let mut tree = PlayerAnimTree(AnimTree::<PlayerAnimState>::new(AnimNode::MatchNode(
         start_node,
@@ -141,7 +164,9 @@ fn build_anim_tree() -> PlayerAnimTree {
         .insert_unwrap(AnimNode::AllNode(land_node))
         .insert_unwrap(jump_anim_node)
         .insert_unwrap(land_anim_node)
-        .insert_unwrap(stand_node);
+        .insert_unwrap(stand_node)
+        .insert_unwrap(hurt_node)
+        .insert_unwrap(wall_slide_node);
 
     tree
 }
@@ -151,14 +176,16 @@ const HURT_TIME: f32 = 0.5;
 
 fn update_animation(
     mut q: Query<(
+        Entity,
         &mut AnimData<PlayerAnimState>,
         &PlayerMovement,
         &mut TextureAtlasSprite,
     )>,
     mut e_reader: EventReader<PlayerEvent>,
 ) {
+    let events: Vec<&PlayerEvent> = e_reader.iter().collect();
     q.iter_mut()
-        .for_each(|(mut anim_data, movement_data, mut sprite)| {
+        .for_each(|(player, mut anim_data, movement_data, mut sprite)| {
             let get_new_state = |data: &AnimData<PlayerAnimState>, new_state: PlayerAnimState| {
                 data.get_state()
                     .eq(&PlayerAnimState::Hurt)
@@ -167,7 +194,8 @@ fn update_animation(
                     .then_some(new_state)
             };
 
-            e_reader.iter().for_each(|e| {
+            let player_events = events.iter().filter(|e| e.player() == player);
+            player_events.for_each(|e| {
                 let state = anim_data.get_state();
                 let anim_state = match e {
                     PlayerEvent::Airborne(_) => PlayerAnimState::MidAir,
@@ -175,8 +203,15 @@ fn update_animation(
                     PlayerEvent::Grounded(_) => PlayerAnimState::Landing,
                     PlayerEvent::Moving(_, _) => PlayerAnimState::Walking,
                     PlayerEvent::Standing(_) => PlayerAnimState::Standing,
+                    PlayerEvent::Hurt(_) => PlayerAnimState::Hurt,
+                    PlayerEvent::WallSliding(_) => PlayerAnimState::WallSliding,
+                    PlayerEvent::WallJumped(_) => PlayerAnimState::WallJumping,
+                    // Only fall back to mid air when the slide ended without a jump
+                    PlayerEvent::WallReleased(_) => state
+                        .eq(&PlayerAnimState::WallSliding)
+                        .then_some(PlayerAnimState::MidAir)
+                        .unwrap_or(PlayerAnimState::None),
                     _ => PlayerAnimState::None,
-                    // PlayerEvent::Hurt(_) => PlayerAnimState::Hurt,
                     // PlayerEvent::Attacks(_) => PlayerAnimState::Hurt,
                 };
 
@@ -186,12 +221,14 @@ fn update_animation(
                         PlayerAnimState::Standing => {
                             state.eq(&PlayerAnimState::Idle).not().then_some(anim_state)
                         }
-                        PlayerAnimState::MidAir => state
-                            .eq(&PlayerAnimState::Jumping)
-                            .not()
-                            .then_some(anim_state),
+                        PlayerAnimState::MidAir => (state.eq(&PlayerAnimState::Jumping)
+                            || state.eq(&PlayerAnimState::WallJumping))
+                        .not()
+                        .then_some(anim_state),
                         PlayerAnimState::Walking => (state.eq(&PlayerAnimState::MidAir)
                             || state.eq(&PlayerAnimState::Jumping)
+                            || state.eq(&PlayerAnimState::WallSliding)
+                            || state.eq(&PlayerAnimState::WallJumping)
                             || state.eq(&PlayerAnimState::Hurt))
                         .not()
                         .then_some(anim_state),
@@ -210,12 +247,22 @@ fn update_animation(
                         .ge(&IDLE_TIME)
                         .then(|| anim_data.set_state(PlayerAnimState::Idle));
                 }
+                PlayerAnimState::Hurt => {
+                    anim_data
+                        .get_state_time()
+                        .ge(&HURT_TIME)
+                        .then(|| anim_data.set_state(PlayerAnimState::Standing));
+                }
                 _ => (),
             };
 
             (movement_data.axis != 0.0).then(|| {
                 match anim_data.get_state() {
-                    PlayerAnimState::Hurt | PlayerAnimState::Jumping | PlayerAnimState::MidAir => {}
+                    PlayerAnimState::Hurt
+                    | PlayerAnimState::Jumping
+                    | PlayerAnimState::MidAir
+                    | PlayerAnimState::WallSliding
+                    | PlayerAnimState::WallJumping => {}
                     _ => {
                         anim_data.set_state(PlayerAnimState::Walking);
                     }
@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::data::{
+    physics::ComplexExternalForce,
+    player::{Player, PlayerCrawl, PlayerInfo, PlayerJump, PlayerWallJump},
+    tilemap::WebStickable,
+};
+
+use super::{PlayerEvent, PlayerInput};
+
+/// Let the player crawl along web stickable terrain while holding the crawl key. Gravity is
+/// replaced by a force into the surface, the body is rotated so its feet face the surface and
+/// input is projected onto the surface tangent.
+pub fn handle_crawl(
+    q_child: Query<&Children>,
+    q_web_stickable: Query<Entity, With<WebStickable>>,
+    mut q_player: Query<
+        (
+            Entity,
+            &PlayerInput,
+            &mut PlayerCrawl,
+            &mut PlayerInfo,
+            &mut PlayerJump,
+            &PlayerWallJump,
+            &mut Transform,
+            &mut Velocity,
+            &mut GravityScale,
+            &mut ComplexExternalForce,
+            &mut ExternalImpulse,
+        ),
+        With<Player>,
+    >,
+    rapier_context: Res<RapierContext>,
+    mut e_writer: EventWriter<PlayerEvent>,
+) {
+    q_player.for_each_mut(
+        |(
+            player,
+            input,
+            mut crawl,
+            mut info,
+            mut jump,
+            wall_jump,
+            mut transform,
+            mut vel,
+            mut gravity_scale,
+            mut cef,
+            mut impulse,
+        )| {
+            // Contact normals are summed so standing in a corner results in a diagonal normal
+            let surface_normal = q_child
+                .iter_descendants(player)
+                .flat_map(|child| {
+                    rapier_context
+                        .contacts_with(child)
+                        .filter(|contact| contact.has_any_active_contacts())
+                        .filter_map(|contact| {
+                            let (other, flip) = (contact.collider1() == child)
+                                .then_some((contact.collider2(), -1.0))
+                                .unwrap_or((contact.collider1(), 1.0));
+                            q_web_stickable.contains(other).then(|| {
+                                contact
+                                    .manifolds()
+                                    .map(|manifold| flip * manifold.normal())
+                                    .sum::<Vec2>()
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .sum::<Vec2>()
+                .normalize_or_zero();
+
+            let was_crawling = info.is_crawling;
+            info.is_crawling = input.crawl && surface_normal != Vec2::ZERO;
+
+            if !info.is_crawling {
+                was_crawling.then(|| {
+                    gravity_scale.0 = wall_jump.normal_gravity_scale;
+                    transform.rotation = Quat::IDENTITY;
+                    cef.clear(crawl.crawl_force);
+                    e_writer.send(PlayerEvent::StoppedCrawling(player));
+                });
+                return;
+            }
+            (!was_crawling).then(|| e_writer.send(PlayerEvent::StartedCrawling(player)));
+
+            crawl.surface_normal = surface_normal;
+            gravity_scale.0 = 0.0;
+            transform.rotation = Quat::from_rotation_z(-surface_normal.angle_between(Vec2::Y));
+
+            // Jumping off pushes the player away from the surface instead of straight up
+            if jump.buffer_counter > 0.0 {
+                jump.buffer_counter = 0.0;
+                jump.is_jumping = true;
+                info.is_crawling = false;
+                gravity_scale.0 = wall_jump.normal_gravity_scale;
+                transform.rotation = Quat::IDENTITY;
+                impulse.impulse = surface_normal * crawl.jump_strength;
+                cef.clear(crawl.crawl_force);
+                e_writer.send(PlayerEvent::StoppedCrawling(player));
+                e_writer.send(PlayerEvent::Jumped(player));
+                return;
+            }
+
+            let input_dir = Vec2::new(input.horizontal(), input.vertical());
+            let tangent = surface_normal.perp();
+            vel.linvel = tangent * input_dir.dot(tangent) * crawl.speed;
+
+            cef.set(crawl.crawl_force, -surface_normal * crawl.stick_force);
+        },
+    );
+}
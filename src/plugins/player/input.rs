@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+
+use crate::{
+    data::player::{Player, PlayerCamera},
+    plugins::{base::CameraMode, utils},
+};
+
+use super::{AimControl, PlayerControl, PlayerInput};
+
+/// How far from the player keyboard aiming points, only the direction matters for shooting
+const KEY_AIM_DISTANCE: f32 = 64.0;
+
+/// Sample the keyboard and mouse every frame for the next physics tick
+pub fn read_player_input(
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    camera_mode: Res<CameraMode>,
+    q_camera: Query<(&Camera, &GlobalTransform, &PlayerCamera)>,
+    mut q_player: Query<(&Player, &PlayerControl, &GlobalTransform, &mut PlayerInput)>,
+) {
+    q_player.for_each_mut(|(player, control, player_transform, mut input)| {
+        input.left = keys.pressed(control.left);
+        input.right = keys.pressed(control.right);
+        input.up = keys.pressed(control.up);
+        input.down = keys.pressed(control.down);
+        input.jump = keys.pressed(control.jump);
+        input.crawl = keys.pressed(control.crawl);
+        input.reel_in = keys.pressed(control.reel_in);
+        input.reel_out = keys.pressed(control.reel_out);
+
+        input.jump_pressed |= keys.just_pressed(control.jump);
+        input.bridge_pressed |= keys.just_pressed(control.bridge);
+
+        match control.aim {
+            AimControl::Mouse => {
+                input.shoot_pressed |= buttons.just_pressed(MouseButton::Left);
+                input.release_pressed |= buttons.just_pressed(MouseButton::Right);
+
+                // The player's own camera when split, the first player's one is shared otherwise
+                let camera_index = match *camera_mode {
+                    CameraMode::Shared => 0,
+                    CameraMode::Split => player.index,
+                };
+                input.cursor = q_camera
+                    .iter()
+                    .find(|(_, _, player_camera)| player_camera.index == camera_index)
+                    .and_then(|(camera, camera_transform, _)| {
+                        utils::cursor_screen_to_world(&windows, camera, camera_transform)
+                    });
+            }
+            AimControl::Keys { shoot, release } => {
+                input.shoot_pressed |= keys.just_pressed(shoot);
+                input.release_pressed |= keys.just_pressed(release);
+
+                let direction = Vec2::new(input.horizontal(), input.vertical())
+                    .try_normalize()
+                    .unwrap_or(Vec2::Y);
+                input.cursor =
+                    Some(player_transform.translation().truncate() + KEY_AIM_DISTANCE * direction);
+            }
+        }
+    });
+}
+
+/// Presses are consumed by the first tick after they happened
+pub fn clear_player_input_presses(mut q_input: Query<&mut PlayerInput>) {
+    q_input.for_each_mut(|mut input| {
+        input.jump_pressed = false;
+        input.bridge_pressed = false;
+        input.shoot_pressed = false;
+        input.release_pressed = false;
+    });
+}
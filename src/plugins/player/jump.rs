@@ -3,22 +3,23 @@ use std::ops::Not;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
-use crate::data::{
-    physics::ComplexExternalForce,
-    player::{Player, PlayerFoot, PlayerHead, PlayerInfo, PlayerJump},
-    tilemap::Platform,
+use crate::{
+    data::{
+        physics::ComplexExternalForce,
+        player::{Player, PlayerFoot, PlayerHead, PlayerInfo, PlayerJump},
+        tilemap::{Platform, SurfaceMaterial},
+    },
+    plugins::physics::FIXED_TIMESTEP,
 };
 
-use super::{PlayerControl, PlayerEvent};
+use super::{PlayerEvent, PlayerInput};
 
 pub fn handle_jump(
-    input: Res<Input<KeyCode>>,
-    time: Res<Time>,
-    control: Res<PlayerControl>,
     mut e_writer: EventWriter<PlayerEvent>,
     mut query: Query<
         (
             Entity,
+            &PlayerInput,
             &mut PlayerJump,
             &PlayerInfo,
             &mut ComplexExternalForce,
@@ -27,35 +28,40 @@ pub fn handle_jump(
         With<Player>,
     >,
 ) {
-    let pressing = input.pressed(control.jump);
-    let just_pressed = input.just_pressed(control.jump);
-
     query.iter_mut().for_each(
-        |(entity, mut jump_com, info_com, mut cef, mut impulse)| {
-            (just_pressed && info_com.is_grounded).then(|| {
+        |(entity, input, mut jump_com, info_com, mut cef, mut impulse)| {
+            let pressing = input.jump;
+            input
+                .jump_pressed
+                .then(|| jump_com.buffer_counter = jump_com.buffer_time);
+            // Jumping off while crawling is handled by the crawl system
+            let can_jump = !info_com.is_crawling
+                && (info_com.is_grounded || jump_com.coyote_counter > 0.0);
+
+            (jump_com.buffer_counter > 0.0 && can_jump).then(|| {
                 impulse.impulse = Vec2::new(impulse.impulse.x, jump_com.strength);
                 jump_com.counter = jump_com.duration;
+                jump_com.buffer_counter = 0.0;
+                jump_com.coyote_counter = 0.0;
+                jump_com.is_jumping = true;
 
                 e_writer.send(PlayerEvent::Jumped(entity));
             });
 
+            jump_com.buffer_counter = (jump_com.buffer_counter - FIXED_TIMESTEP).max(0.0);
+            jump_com.coyote_counter = (jump_com.coyote_counter - FIXED_TIMESTEP).max(0.0);
+
             (jump_com.counter > 0.0).then(|| {
                 pressing
                     .then(|| {
-                        cef.forces
-                            .entry(jump_com.jump_force_id)
-                            .and_modify(|jump_force| {
-                                jump_force.y = jump_com.air_upward_force
-                                    * (jump_com.counter / jump_com.duration)
-                            });
-                        jump_com.counter -= time.delta_seconds();
+                        cef.modify(jump_com.jump_force, |jump_force| {
+                            jump_force.y = jump_com.air_upward_force
+                                * (jump_com.counter / jump_com.duration)
+                        });
+                        jump_com.counter -= FIXED_TIMESTEP;
                     })
                     .unwrap_or_else(|| {
-                        cef.forces
-                            .entry(jump_com.jump_force_id)
-                            .and_modify(|jump_force| {
-                                jump_force.y = 0.0
-                            });
+                        cef.modify(jump_com.jump_force, |jump_force| jump_force.y = 0.0);
                         jump_com.counter = 0.0;
                     });
             });
@@ -63,6 +69,30 @@ pub fn handle_jump(
     );
 }
 
+/// Open the coyote time window when the player walks off a ledge and close it on landing
+pub fn update_coyote_time(
+    mut e_reader: EventReader<PlayerEvent>,
+    mut query: Query<&mut PlayerJump, With<Player>>,
+) {
+    e_reader.iter().for_each(|e| match e {
+        PlayerEvent::Airborne(player) => {
+            if let Ok(mut jump_com) = query.get_mut(*player) {
+                jump_com
+                    .is_jumping
+                    .not()
+                    .then(|| jump_com.coyote_counter = jump_com.coyote_time);
+            }
+        }
+        PlayerEvent::Grounded(player) => {
+            if let Ok(mut jump_com) = query.get_mut(*player) {
+                jump_com.coyote_counter = 0.0;
+                jump_com.is_jumping = false;
+            }
+        }
+        _ => (),
+    });
+}
+
 pub fn check_if_head_bump(
     q_child: Query<&Children>,
     q_head: Query<Entity, (With<PlayerHead>, With<Sensor>)>,
@@ -70,8 +100,6 @@ pub fn check_if_head_bump(
     mut q_player: Query<(Entity, &mut PlayerJump, &mut ComplexExternalForce), With<Player>>,
     rapier_context: Res<RapierContext>,
 ) {
-    let (player, mut player_jump, mut cef) = q_player.single_mut();
-
     let check_head_then =
         |child: Entity, func: &dyn Fn() -> bool| {
             q_head.contains(child).then(func).unwrap_or(false)
@@ -87,55 +115,61 @@ pub fn check_if_head_bump(
         })
     };
 
-    q_child
-        .iter_descendants(player)
-        .all(check_not_collide)
-        .not()
-        .then(|| {
-            player_jump.counter = 0.0;
-            cef.forces.entry(player_jump.jump_force_id).and_modify(|jump_force| {
-                jump_force.y = 0.0;
+    q_player.for_each_mut(|(player, mut player_jump, mut cef)| {
+        q_child
+            .iter_descendants(player)
+            .all(&check_not_collide)
+            .not()
+            .then(|| {
+                player_jump.counter = 0.0;
+                cef.modify(player_jump.jump_force, |jump_force| {
+                    jump_force.y = 0.0;
+                });
             });
-        });
+    });
 }
 
 pub fn check_if_grounded(
     q_child: Query<&Children>,
     q_foot: Query<Entity, (With<PlayerFoot>, With<Sensor>)>,
     q_platform: Query<&Collider, With<Platform>>,
+    q_surface: Query<&SurfaceMaterial>,
     mut q_player: Query<(Entity, &mut PlayerInfo), With<Player>>,
     rapier_context: Res<RapierContext>,
     mut e_writer: EventWriter<PlayerEvent>,
 ) {
-    let (player, mut player_info) = q_player.single_mut();
-
-    let check_foot_then =
-        |child: Entity, func: &dyn Fn() -> bool| {
-            q_foot.contains(child).then(func).unwrap_or(false)
-        };
-
-    let check_not_collide = |child: Entity| {
-        !check_foot_then(child, &|| {
-            rapier_context
-                .intersections_with(child)
-                .any(|(entity1, entity2, _)| {
-                    q_platform.contains(entity1) || q_platform.contains(entity2)
-                })
-        })
+    // Platform a foot sensor of the player touches
+    let ground_under = |child: Entity| {
+        q_foot
+            .contains(child)
+            .then(|| {
+                rapier_context
+                    .intersections_with(child)
+                    .find_map(|(entity1, entity2, _)| {
+                        [entity1, entity2]
+                            .into_iter()
+                            .find(|entity| q_platform.contains(*entity))
+                    })
+            })
+            .flatten()
     };
 
-    let old_value = player_info.is_grounded;
-    player_info.is_grounded = q_child
-        .iter_descendants(player)
-        .all(check_not_collide)
-        .not();
-
-    (old_value != player_info.is_grounded).then(|| {
-        e_writer.send(
-            player_info
-                .is_grounded
-                .then_some(PlayerEvent::Grounded(player))
-                .unwrap_or(PlayerEvent::Airborne(player)),
-        )
+    q_player.for_each_mut(|(player, mut player_info)| {
+        let old_value = player_info.is_grounded;
+        let ground = q_child.iter_descendants(player).find_map(&ground_under);
+        player_info.is_grounded = ground.is_some();
+        player_info.surface = ground
+            .and_then(|ground| q_surface.get(ground).ok())
+            .copied()
+            .unwrap_or_default();
+
+        (old_value != player_info.is_grounded).then(|| {
+            e_writer.send(
+                player_info
+                    .is_grounded
+                    .then_some(PlayerEvent::Grounded(player))
+                    .unwrap_or(PlayerEvent::Airborne(player)),
+            )
+        });
     });
 }
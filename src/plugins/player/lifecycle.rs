@@ -1,18 +1,16 @@
-use std::collections::HashMap;
-
 use bevy::prelude::*;
 use bevy_ecs_ldtk::LevelSelection;
 use bevy_rapier2d::prelude::{
     ActiveEvents, Ccd, CoefficientCombineRule, Collider, ColliderMassProperties, CollisionEvent,
-    CollisionGroups, Friction, GravityScale, LockedAxes,
+    CollisionGroups, Friction, GravityScale, LockedAxes, RapierContext, Velocity,
 };
 
 use crate::{
     data::{physics::*, player::*, tilemap::Trap},
-    plugins::tilemap,
+    plugins::{physics::FIXED_TIMESTEP, tilemap},
 };
 
-use super::PlayerEvent;
+use super::{GameMode, PlayerControl, PlayerEvent};
 
 const PLAYER_NAME: &str = "Player";
 const PLAYER_SIZE: Vec2 = Vec2::splat(24.0);
@@ -21,12 +19,25 @@ const PLAYER_SPRITE_PATH: &str = "spider_sprite_sheet.png";
 const PLAYER_SPRITE_ROW_COL: [usize; 2] = [9, 16];
 const PLAYER_SPRITE_PADDING: Option<Vec2> = None;
 const PLAYER_SPRITE_OFFSET: Option<Vec2> = None;
+const PLAYER_GRAVITY_SCALE: f32 = 2.5;
+const PLAYER_MAX_HEALTH: i32 = 3;
+/// About the push of an 80 by 120 impulse, spread over the time the force takes to fade
+const PLAYER_KNOCKBACK: Vec2 = Vec2::new(370.0, 550.0);
+/// Fraction of the knockback force lost per second
+const PLAYER_KNOCKBACK_DECAY: f32 = 0.99;
+const PLAYER_INVULNERABLE_TIME: f32 = 1.0;
+const FLICKER_INTERVAL: f32 = 0.1;
+const FLICKER_ALPHA: f32 = 0.2;
+/// Tint of each player so local co-op players can tell their spiders apart
+const PLAYER_COLORS: [Color; 2] = [Color::WHITE, Color::rgb(1.0, 0.7, 0.5)];
 
 pub fn spawn_player(
     commands: &mut Commands,
+    index: usize,
     transform: Transform,
     asset_server: &AssetServer,
     texture_atlases: &mut Assets<TextureAtlas>,
+    game_mode: GameMode,
 ) -> Entity {
     let texture_handle = asset_server.load(PLAYER_SPRITE_PATH);
     let texture_atlas = TextureAtlas::from_grid(
@@ -39,46 +50,84 @@ pub fn spawn_player(
     );
     let texture_atlas_handle = texture_atlases.add(texture_atlas);
 
-    commands
-        .spawn(PlayerBundle {
-            sprite: SpriteSheetBundle {
-                transform,
-                texture_atlas: texture_atlas_handle,
-                sprite: TextureAtlasSprite {
-                    custom_size: Some(PLAYER_SIZE),
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-            physics: RigidBodyBundle {
-                complex_ef: ComplexExternalForce {
-                    forces: HashMap::from([(0, Vec2::ZERO), (1, Vec2::ZERO)]),
-                },
-                rotation_constraints: LockedAxes::ROTATION_LOCKED_Z,
-                gravity_scale: GravityScale(2.5),
-                ..Default::default()
-            },
-            jump: PlayerJump {
-                jump_force_id: 1,
-                strength: 150.0,
-                air_upward_force: 1400.0,
-                duration: 0.4,
-                ..Default::default()
-            },
-            movement: PlayerMovement {
-                movement_force_id: 0,
-                acceleration: 1300.0,
-                landing_accel: 200.0,
-                airborne_acceleration: PlayerMovement::NORM_AIR_ACCEL,
-                max_velocity: 60.0,
+    let mut complex_ef = ComplexExternalForce::default();
+    let movement_force = complex_ef.register("movement");
+    let jump_force = complex_ef.register("jump");
+    let crawl_force = complex_ef.register("crawl");
+    let knockback_force = (game_mode == GameMode::Health)
+        .then(|| complex_ef.register_with_decay("knockback", PLAYER_KNOCKBACK_DECAY));
+
+    let mut player = commands.spawn(PlayerBundle {
+        sprite: SpriteSheetBundle {
+            transform,
+            texture_atlas: texture_atlas_handle,
+            sprite: TextureAtlasSprite {
+                color: PLAYER_COLORS[index % PLAYER_COLORS.len()],
+                custom_size: Some(PLAYER_SIZE),
                 ..Default::default()
             },
-            info: PlayerInfo { is_grounded: true },
-            name: Name::from(PLAYER_NAME),
             ..Default::default()
-        })
+        },
+        physics: RigidBodyBundle {
+            complex_ef,
+            rotation_constraints: LockedAxes::ROTATION_LOCKED_Z,
+            gravity_scale: GravityScale(PLAYER_GRAVITY_SCALE),
+            ..Default::default()
+        },
+        player: Player { index },
+        jump: PlayerJump {
+            jump_force,
+            strength: 150.0,
+            air_upward_force: 1400.0,
+            duration: 0.4,
+            coyote_time: 0.1,
+            buffer_time: 0.12,
+            ..Default::default()
+        },
+        wall_jump: PlayerWallJump {
+            impulse: Vec2::new(90.0, 140.0),
+            slide_gravity_scale: 1.0,
+            cling_gravity_scale: 0.2,
+            normal_gravity_scale: PLAYER_GRAVITY_SCALE,
+            slide_max_velocity: 50.0,
+            cling_max_velocity: 10.0,
+            control_lock_time: 0.2,
+            ..Default::default()
+        },
+        crawl: PlayerCrawl {
+            crawl_force,
+            speed: 40.0,
+            stick_force: 600.0,
+            jump_strength: 120.0,
+            ..Default::default()
+        },
+        movement: PlayerMovement {
+            movement_force,
+            acceleration: 1300.0,
+            landing_accel: 200.0,
+            airborne_acceleration: PlayerMovement::NORM_AIR_ACCEL,
+            max_velocity: 60.0,
+            ..Default::default()
+        },
+        info: PlayerInfo {
+            is_grounded: true,
+            ..Default::default()
+        },
+        name: Name::from(format!("{} {}", PLAYER_NAME, index + 1)),
+        ..Default::default()
+    });
+    if let Some(knockback_force) = knockback_force {
+        player.insert(PlayerHealth::new(
+            PLAYER_MAX_HEALTH,
+            PLAYER_KNOCKBACK,
+            knockback_force,
+            PLAYER_INVULNERABLE_TIME,
+        ));
+    }
+
+    player
         .insert(Ccd::enabled())
-        .insert(Name::from("Player"))
+        .insert(PlayerControl::for_player(index))
         .with_children(|builder| {
             builder
                 .spawn(TransformBundle {
@@ -177,70 +226,195 @@ pub fn spawn_player(
                 name: Name::from("Foot wide"),
                 ..Default::default()
             });
+
+            builder.spawn(PlayerWallBundle {
+                transform: TransformBundle {
+                    local: Transform::from_xyz(-4.5, -3.3, 0.0),
+                    ..Default::default()
+                },
+                player_collider: PlayerColliderBundle {
+                    collider: Collider::cuboid(0.8, 1.5),
+                    ..default()
+                },
+                wall_comp: PlayerWall { side: -1.0 },
+                name: Name::from("Wall left"),
+                ..Default::default()
+            });
+
+            builder.spawn(PlayerWallBundle {
+                transform: TransformBundle {
+                    local: Transform::from_xyz(4.5, -3.3, 0.0),
+                    ..Default::default()
+                },
+                player_collider: PlayerColliderBundle {
+                    collider: Collider::cuboid(0.8, 1.5),
+                    ..default()
+                },
+                wall_comp: PlayerWall { side: 1.0 },
+                name: Name::from("Wall right"),
+                ..Default::default()
+            });
         })
         .id()
 }
 
-pub fn kill_player(
-    q_player: Query<Entity, With<Player>>,
-    q_trap: Query<Entity, With<Trap>>,
-    q_parents: Query<&Parent>,
-    mut evr_collisions: EventReader<CollisionEvent>,
-    mut evw_death: EventWriter<PlayerEvent>,
+/// Hurt the player while touching a [`Trap`], without [`PlayerHealth`] or in [`GameMode::Classic`]
+/// the player dies instantly. Traps pointing somewhere only hurt from that side.
+pub fn damage_player(
+    game_mode: Res<GameMode>,
+    rapier_context: Res<RapierContext>,
+    mut q_player: Query<
+        (
+            Entity,
+            Option<&mut PlayerHealth>,
+            &Children,
+            &Velocity,
+            &mut ComplexExternalForce,
+        ),
+        With<Player>,
+    >,
+    q_trap: Query<&Trap>,
+    mut evw_player: EventWriter<PlayerEvent>,
 ) {
-    if evr_collisions.is_empty() {
-        return;
-    }
-    let player = q_player.single();
-    let player_is_parent = |entity: &Entity| {
-        if let Ok(parent) = q_parents.get(*entity) {
-            return parent.get() == player;
-        } else {
-            false
-        }
-    };
-    for collision in evr_collisions.iter() {
-        if let CollisionEvent::Started(entity_one, entity_two, _) = collision {
-            let other_entity = player_is_parent(entity_one)
-                .then_some(*entity_two)
-                .or_else(|| player_is_parent(entity_one).then_some(*entity_one));
-            if let None = other_entity {
-                continue;
-            }
-            let other_entity = other_entity.unwrap();
-            q_trap.get(other_entity).is_ok().then(|| {
-                evw_death.send(PlayerEvent::Died(player));
+    q_player.for_each_mut(|(player, mut health, children, velocity, mut cef)| {
+        // Touching several traps on the same tick only counts the first one
+        let Some(trap) = children
+            .iter()
+            .find_map(|collider| touched_trap(&rapier_context, &q_trap, *collider))
+        else {
+            return;
+        };
+
+        let health = match (*game_mode, health.as_mut()) {
+            (GameMode::Health, Some(health)) => health,
+            _ => {
+                evw_player.send(PlayerEvent::Died(player));
                 return;
-            });
+            }
+        };
+        if health.is_invulnerable() || health.current <= 0 {
+            return;
         }
-    }
+
+        health.current -= trap.damage;
+        if health.current <= 0 {
+            evw_player.send(PlayerEvent::Died(player));
+            return;
+        }
+
+        // Push the player back against the direction they ran into the trap
+        let direction = velocity.linvel.x.gt(&0.0).then_some(-1.0).unwrap_or(1.0);
+        cef.set(
+            health.knockback_force,
+            Vec2::new(direction * health.knockback.x, health.knockback.y),
+        );
+        health.invulnerable_counter = health.invulnerable_duration;
+        evw_player.send(PlayerEvent::Hurt(player));
+    });
 }
 
-pub fn respawn_player_on_death(
-    mut q_player: Query<&mut Transform, With<Player>>,
-    level_selection: Res<LevelSelection>,
-    mut evr_death: EventReader<PlayerEvent>,
+/// A trap the collider is in contact with, spikes are harmless when touched from behind their
+/// base or along their sides
+fn touched_trap<'a>(
+    rapier_context: &RapierContext,
+    q_trap: &'a Query<&Trap>,
+    collider: Entity,
+) -> Option<&'a Trap> {
+    rapier_context
+        .contacts_with(collider)
+        .filter(|contact_pair| contact_pair.has_any_active_contacts())
+        .find_map(|contact_pair| {
+            let (other, sign) = if contact_pair.collider1() == collider {
+                (contact_pair.collider2(), -1.0)
+            } else {
+                (contact_pair.collider1(), 1.0)
+            };
+            let trap = q_trap.get(other).ok()?;
+            let Some(points) = trap.points else {
+                return Some(trap);
+            };
+            // Contact normals point from the first collider to the second, flipped here to
+            // point from the trap to the player
+            contact_pair
+                .manifolds()
+                .filter(|manifold| manifold.num_points() > 0)
+                .any(|manifold| (manifold.normal() * sign).dot(points) > 0.0)
+                .then_some(trap)
+        })
+}
+
+/// The player owning one of the two colliding entities, along with the other entity
+fn player_and_other(
+    q_parents: &Query<&Parent>,
+    is_player: impl Fn(Entity) -> bool,
+    entity_one: Entity,
+    entity_two: Entity,
+) -> Option<(Entity, Entity)> {
+    player_of_collider(q_parents, &is_player, entity_one)
+        .map(|player| (player, entity_two))
+        .or_else(|| {
+            player_of_collider(q_parents, &is_player, entity_two).map(|player| (player, entity_one))
+        })
+}
+
+/// The player a collider belongs to, player colliders are its direct children
+pub fn player_of_collider(
+    q_parents: &Query<&Parent>,
+    is_player: impl Fn(Entity) -> bool,
+    collider: Entity,
+) -> Option<Entity> {
+    q_parents
+        .get(collider)
+        .ok()
+        .map(|parent| parent.get())
+        .filter(|parent| is_player(*parent))
+}
+
+/// Count down invulnerability frames and flicker the sprite while they last
+pub fn update_invulnerability(
+    mut q_player: Query<(&mut PlayerHealth, &mut TextureAtlasSprite), With<Player>>,
 ) {
-    evr_death.iter().find(|ev| match ev {
-        PlayerEvent::Died(_) => {
-            respawn_player(&mut q_player, &*level_selection);
-            true
+    q_player.for_each_mut(|(mut health, mut sprite)| {
+        if !health.is_invulnerable() {
+            sprite.color.set_a(1.0);
+            return;
         }
-        _ => false,
+        health.invulnerable_counter -= FIXED_TIMESTEP;
+
+        let visible = (health.invulnerable_counter / FLICKER_INTERVAL) as i32 % 2 == 0;
+        sprite
+            .color
+            .set_a(visible.then_some(1.0).unwrap_or(FLICKER_ALPHA));
     });
 }
 
-fn respawn_player(
-    q_player: &mut Query<&mut Transform, With<Player>>,
-    level_selection: &LevelSelection,
+pub fn respawn_player_on_death(
+    mut q_player: Query<
+        (
+            &mut Transform,
+            &mut ComplexExternalForce,
+            Option<&mut PlayerHealth>,
+        ),
+        With<Player>,
+    >,
+    level_selection: Res<LevelSelection>,
+    mut evr_death: EventReader<PlayerEvent>,
 ) {
-    match tilemap::current_level_index(level_selection) {
-        Some(level_index) => {
-            let mut player = q_player.single_mut();
-            player.translation = translation_in_level(level_index);
+    for ev in evr_death.iter() {
+        let PlayerEvent::Died(player) = ev else {
+            continue;
+        };
+        let Ok((mut transform, mut cef, health)) = q_player.get_mut(*player) else {
+            continue;
+        };
+        if let Some(level_index) = tilemap::current_level_index(&level_selection) {
+            transform.translation = translation_in_level(level_index);
         }
-        _ => (),
-    };
+        if let Some(mut health) = health {
+            cef.clear(health.knockback_force);
+            health.reset();
+        }
+    }
 }
 
 pub fn translation_in_level(level_index: usize) -> Vec3 {
@@ -261,27 +435,14 @@ pub fn player_collision(
     mut evr_collisions: EventReader<CollisionEvent>,
     mut evw_player: EventWriter<PlayerEvent>,
 ) {
-    if evr_collisions.is_empty() {
-        return;
-    }
-    let player = q_player.single();
-    let player_is_parent = |entity: &Entity| {
-        if let Ok(parent) = q_parents.get(*entity) {
-            return parent.get() == player;
-        } else {
-            false
-        }
-    };
     for collision in evr_collisions.iter() {
         if let CollisionEvent::Started(entity_one, entity_two, _) = collision {
-            let other_entity = player_is_parent(entity_one)
-                .then_some(*entity_two)
-                .or_else(|| player_is_parent(entity_one).then_some(*entity_one));
-            if let None = other_entity {
-                continue;
+            let is_player = |entity| q_player.contains(entity);
+            if let Some((player, other_entity)) =
+                player_and_other(&q_parents, is_player, *entity_one, *entity_two)
+            {
+                evw_player.send(PlayerEvent::Collided(player, other_entity));
             }
-            let other_entity = other_entity.unwrap();
-            evw_player.send(PlayerEvent::Collided(other_entity));
         }
     }
 }
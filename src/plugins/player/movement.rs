@@ -6,20 +6,20 @@ use bevy_rapier2d::prelude::{ExternalForce, Velocity};
 
 use crate::data::{
     physics::ComplexExternalForce,
-    player::{Player, PlayerInfo, PlayerMovement},
+    player::{Player, PlayerInfo, PlayerMovement, PlayerWallJump},
     web::Web,
 };
 
-use super::{PlayerControl, PlayerEvent};
+use super::{PlayerEvent, PlayerInput};
 
 pub fn handle_movement(
-    input: Res<Input<KeyCode>>,
-    control: Res<PlayerControl>,
     mut query: Query<
         (
             Entity,
+            &PlayerInput,
             &mut PlayerMovement,
             &PlayerInfo,
+            &PlayerWallJump,
             &mut Velocity,
             &mut ComplexExternalForce,
         ),
@@ -28,50 +28,59 @@ pub fn handle_movement(
     q_web: Query<&Web>,
     mut e_writer: EventWriter<PlayerEvent>,
 ) {
-    let (player, mut movement, info, mut vel, mut cef) = query.single_mut();
-    let old_value = movement.axis;
+    query.for_each_mut(
+        |(player, input, mut movement, info, wall_jump, mut vel, mut cef)| {
+            // Don't fight the wall jump impulse right after leaving the wall, crawling
+            // moves the player along the surface on its own
+            let controls_locked = wall_jump.control_lock_counter > 0.0 || info.is_crawling;
+            let surface = info.surface;
+            let old_value = movement.axis;
 
-    movement.axis = input.pressed(control.left).then_some(-1.0).unwrap_or(0.0)
-        + input.pressed(control.right).then_some(1.0).unwrap_or(0.0);
+            movement.axis = input.horizontal();
 
-    // Reduce vel when change direction or stop moving but still
-    // still keep the possibility of speed boost.
-    let vel_vec = old_value * movement.axis;
-    let linvel = vel.linvel.clone();
-    let reduce_vel = || {
-        vel.linvel = Vec2::new(0.0, vel.linvel.y);
-    };
-    let web_attached = q_web
-        .is_empty()
-        .not()
-        .then_some(|| q_web.single().attached.then_some(true))
-        .is_some();
-    ((info.is_grounded || !web_attached) && vel_vec <= 0.0).then(reduce_vel);
+            // Reduce vel when change direction or stop moving but still
+            // still keep the possibility of speed boost. Slippery ground only takes
+            // part of the speed and conveyors carry the player along.
+            let vel_vec = old_value * movement.axis;
+            let linvel = vel.linvel.clone();
+            let reduce_vel = || {
+                vel.linvel.x = surface.conveyor_velocity
+                    + (vel.linvel.x - surface.conveyor_velocity)
+                        * (1.0 - surface.friction.min(1.0));
+            };
+            let web_attached = q_web
+                .iter()
+                .any(|web| web.owner == Some(player) && !web.is_bridge());
+            (!controls_locked && (info.is_grounded || !web_attached) && vel_vec <= 0.0)
+                .then(reduce_vel);
 
-    cef.forces
-        .entry(movement.movement_force_id)
-        .and_modify(|move_force| {
-            move_force.x = (linvel.x.abs() < movement.max_velocity)
-                .then_some(
-                    movement.axis
-                        * info
-                            .is_grounded
-                            .then_some(movement.acceleration)
-                            .unwrap_or(movement.airborne_acceleration),
-                )
-                .unwrap_or(0.0)
-        });
+            cef.modify(movement.movement_force, |move_force| {
+                let max_velocity = movement.max_velocity * surface.speed_scale;
+                move_force.x = (!controls_locked
+                    && (linvel.x - surface.conveyor_velocity).abs() < max_velocity)
+                    .then_some(
+                        movement.axis
+                            * surface.speed_scale
+                            * info
+                                .is_grounded
+                                .then_some(movement.acceleration)
+                                .unwrap_or(movement.airborne_acceleration),
+                    )
+                    .unwrap_or(0.0)
+            });
 
-    // Send event
-    old_value.eq(&movement.axis).not().then(|| {
-        movement
-            .axis
-            .eq(&0.0)
-            .then(|| {
-                e_writer.send(PlayerEvent::Standing(player));
-            })
-            .unwrap_or_else(|| e_writer.send(PlayerEvent::Moving(movement.axis, player)))
-    });
+            // Send event
+            old_value.eq(&movement.axis).not().then(|| {
+                movement
+                    .axis
+                    .eq(&0.0)
+                    .then(|| {
+                        e_writer.send(PlayerEvent::Standing(player));
+                    })
+                    .unwrap_or_else(|| e_writer.send(PlayerEvent::Moving(movement.axis, player)))
+            });
+        },
+    );
 }
 
 pub fn apply_accel_when_land(
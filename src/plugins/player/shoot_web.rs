@@ -1,199 +1,568 @@
+use std::collections::HashSet;
+
 use crate::data::physics::ComplexExternalForce;
-use crate::data::player::PlayerMovement;
-use crate::data::tilemap::WebStickable;
+use crate::data::platform::MovingBody;
+use crate::data::player::{PlayerAbilities, PlayerInfo, PlayerMovement};
+use crate::data::tilemap::{WebGrabbable, WebStickable};
 use crate::data::web::*;
 use crate::data::{
     physics::{CollisionGroupsFilter, GameCollisionGroups},
     player::Player,
     web::{Web, WebHead, WebString},
 };
-use crate::plugins::utils;
+use crate::plugins::physics::FIXED_TIMESTEP;
 use bevy::prelude::*;
-use bevy::sprite::MaterialMesh2dBundle;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
 use bevy_rapier2d::prelude::*;
 
-use super::PlayerEvent;
+use super::{
+    aim::resolve_aim, lifecycle::player_of_collider, PlayerEvent, PlayerInput, WebEvent,
+    WebGrabEvent, WebPhysics, WebSettings,
+};
 
 const WEB_SPRITE_PATH: &str = "web.png";
 const WEB_SHOOT_SPEED: f32 = 150.0;
 const WEB_Z: f32 = 100.0;
-const WEB_PULL_FORCE_SCALE: f32 = 110.0;
+pub const WEB_PULL_FORCE_SCALE: f32 = 110.0;
+const WEB_BEND_MARGIN: f32 = 1.0;
 
 #[derive(Resource, Default)]
 pub struct WebTexture(Handle<Image>);
 
-pub struct DespawnWebEvent;
+/// Id given to the next shot web
+#[derive(Resource, Default)]
+pub struct WebCounter(u32);
+
+pub enum DespawnWebEvent {
+    /// The most recently shot web of the player that isn't a bridge
+    Newest(Entity),
+    /// Every web still attached to the player, bridges stay in place
+    AllFromPlayer(Entity),
+    Web(Entity),
+}
 
 pub fn setup_web_texture(asset_server: Res<AssetServer>, mut web_texture: ResMut<WebTexture>) {
     web_texture.0 = asset_server.load(WEB_SPRITE_PATH);
 }
 
 pub fn handle_shoot_web_input(
-    input: Res<Input<MouseButton>>,
+    q_player: Query<(Entity, &PlayerInput), With<Player>>,
     mut evw_web: EventWriter<PlayerEvent>,
     mut evw_despawn_web: EventWriter<DespawnWebEvent>,
 ) {
-    if input.just_pressed(MouseButton::Left) {
-        evw_web.send(PlayerEvent::ShotWeb);
-        debug!("!shot web");
-    } else if input.just_pressed(MouseButton::Right) {
-        evw_despawn_web.send(DespawnWebEvent);
-        debug!("!released web");
-    }
+    q_player.for_each(|(player, input)| {
+        if input.shoot_pressed {
+            evw_web.send(PlayerEvent::ShotWeb(player));
+            debug!("!shot web");
+        } else if input.release_pressed {
+            evw_despawn_web.send(DespawnWebEvent::Newest(player));
+            debug!("!released web");
+        }
+    });
 }
 
 /// Shoot the web-head out with a certain velocity, the "string" of the web starts out with 0 length, which will
 /// later be adjusted by [`update_web_string_transform`].
 pub fn shoot_web(
     q_web_string: Query<&Handle<ColorMaterial>, With<WebString>>,
-    q_web: Query<Entity, With<Web>>,
-    q_player: Query<&GlobalTransform, With<Player>>,
-    q_camera: Query<(&Camera, &GlobalTransform)>,
+    q_web: Query<(Entity, &Web)>,
+    mut q_player: Query<(&GlobalTransform, &PlayerInput, &mut PlayerAbilities), With<Player>>,
+    q_players: Query<Entity, With<Player>>,
+    q_parents: Query<&Parent>,
+    q_web_stickable: Query<Entity, With<WebStickable>>,
     web_texture: Res<WebTexture>,
+    web_settings: Res<WebSettings>,
+    mut web_counter: ResMut<WebCounter>,
+    rapier_context: Res<RapierContext>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    windows: Res<Windows>,
     mut evr_player_action: EventReader<PlayerEvent>,
+    mut evw_despawn_web: EventWriter<DespawnWebEvent>,
+    mut evw_web: EventWriter<WebEvent>,
     mut commands: Commands,
 ) {
-    evr_player_action
+    // One shot per player and tick
+    let mut shooters: Vec<Entity> = evr_player_action
         .iter()
-        .any(|ev| match ev {
-            PlayerEvent::ShotWeb => true,
-            _ => false,
+        .filter_map(|ev| match ev {
+            PlayerEvent::ShotWeb(player) => Some(*player),
+            _ => None,
         })
-        .then(|| {
-            if !q_web.is_empty() {
+        .collect();
+    shooters.sort();
+    shooters.dedup();
+    shooters.into_iter().for_each(|player| {
+        let Ok((player_transform, input, mut abilities)) = q_player.get_mut(player) else {
+            return;
+        };
+        if abilities.web_cooldown_counter > 0.0 {
+            return;
+        }
+        if let Some(cursor_translation) = input.cursor {
+            let player_translation = player_transform.translation().truncate();
+            let Some(aim) = resolve_aim(
+                player,
+                player_translation,
+                cursor_translation,
+                web_settings.reach(&abilities),
+                &web_settings,
+                &rapier_context,
+                |entity| {
+                    q_web_stickable.contains(entity)
+                        || player_of_collider(&q_parents, |e| q_players.contains(e), entity)
+                            .is_some()
+                },
+            ) else {
                 return;
-            }
-            let (camera, camera_transform) = q_camera.single();
-            if let Some(cursor_translation) =
-                utils::cursor_screen_to_world(&windows, &camera, &camera_transform)
-            {
-                q_web.for_each(|entity| {
-                    commands.entity(entity).despawn_recursive();
-                });
+            };
 
-                let player_translation = q_player.single().translation().truncate();
-                let shoot_direction = (cursor_translation - player_translation).normalize();
-                let shoot_translation = player_translation + 4.0 * shoot_direction;
-                let (midpoint, angle) =
-                    midpoint_and_angle_to_x(player_translation, shoot_translation);
-
-                let mat_handle = q_web_string
-                    .is_empty()
-                    .then(|| materials.add(ColorMaterial::from(Color::WHITE)).clone())
-                    .unwrap_or_else(|| q_web_string.single().clone());
-                let mesh_handle = meshes.add(shape::Box::new(1.0, 1.0, 1.0).into());
-
-                commands
-                    .spawn(SpatialBundle::default())
-                    .insert(Web::default())
-                    .with_children(|child_builder| {
-                        child_builder
-                            .spawn(WebHeadBundle {
-                                web_head: WebHead::default(),
-                                sprite: SpriteBundle {
-                                    texture: web_texture.0.clone(),
-                                    sprite: Sprite {
-                                        custom_size: Some(8.0 * Vec2::ONE),
-                                        ..default()
-                                    },
-                                    transform: Transform::from_translation(
-                                        shoot_translation.extend(WEB_Z + 1.0),
-                                    ),
+            // Make room for the new web by dropping the player's oldest ones
+            let mut webs: Vec<_> = q_web
+                .iter()
+                .filter(|(_, web)| web.owner == Some(player))
+                .collect();
+            webs.sort_by_key(|(_, web)| web.id);
+            let excess = (webs.len() + 1).saturating_sub(web_settings.max_webs.max(1));
+            webs.iter().take(excess).for_each(|(entity, _)| {
+                evw_despawn_web.send(DespawnWebEvent::Web(*entity));
+            });
+
+            let shoot_direction = aim.direction;
+            let shoot_translation = player_translation + 4.0 * shoot_direction;
+            let (midpoint, angle) = midpoint_and_angle_to_x(player_translation, shoot_translation);
+
+            let mat_handle = q_web_string
+                .iter()
+                .next()
+                .cloned()
+                .unwrap_or_else(|| materials.add(ColorMaterial::from(Color::WHITE)));
+            let mesh_handle = meshes.add(shape::Box::new(1.0, 1.0, 1.0).into());
+
+            commands
+                .spawn(SpatialBundle::default())
+                .insert(Web {
+                    id: web_counter.0,
+                    owner: Some(player),
+                    ..default()
+                })
+                .with_children(|child_builder| {
+                    child_builder
+                        .spawn(WebHeadBundle {
+                            web_head: WebHead::default(),
+                            sprite: SpriteBundle {
+                                texture: web_texture.0.clone(),
+                                sprite: Sprite {
+                                    custom_size: Some(8.0 * Vec2::ONE),
                                     ..default()
                                 },
-                                collider: Collider::ball(0.1),
-                                velocity: Velocity::linear(WEB_SHOOT_SPEED * shoot_direction),
-                                g_scale: GravityScale(0.0),
-                                collision_groups: CollisionGroups {
-                                    memberships: GameCollisionGroups::WEB,
-                                    filters: GameCollisionGroups::WEB.filter_group(),
-                                },
-                                active_events: ActiveEvents::COLLISION_EVENTS,
+                                transform: Transform::from_translation(
+                                    shoot_translation.extend(WEB_Z + 1.0),
+                                ),
                                 ..default()
-                            })
-                            .insert(Name::from("Web head"));
-                        child_builder
-                            .spawn(WebStringBundle {
-                                visual: MaterialMesh2dBundle {
-                                    mesh: mesh_handle.into(),
-                                    material: mat_handle,
-                                    transform: Transform {
-                                        translation: midpoint.extend(WEB_Z),
-                                        rotation: Quat::from_rotation_z(-angle),
-                                        scale: Vec3::new(0.0, 0.2, 1.0),
-                                    },
-                                    ..default()
+                            },
+                            collider: Collider::ball(0.1),
+                            velocity: Velocity::linear(
+                                WEB_SHOOT_SPEED * abilities.shoot_speed_scale() * shoot_direction,
+                            ),
+                            g_scale: GravityScale(0.0),
+                            collision_groups: CollisionGroups {
+                                memberships: GameCollisionGroups::WEB,
+                                filters: GameCollisionGroups::WEB.filter_group(),
+                            },
+                            active_events: ActiveEvents::COLLISION_EVENTS,
+                            ..default()
+                        })
+                        .insert(Name::from("Web head"));
+                    child_builder
+                        .spawn(WebStringBundle {
+                            visual: MaterialMesh2dBundle {
+                                mesh: mesh_handle.into(),
+                                material: mat_handle,
+                                transform: Transform {
+                                    translation: midpoint.extend(WEB_Z),
+                                    rotation: Quat::from_rotation_z(-angle),
+                                    scale: Vec3::new(0.0, 0.2, 1.0),
                                 },
                                 ..default()
-                            })
-                            .insert(Name::from("Web string"));
-                    });
+                            },
+                            web_string: WebString { segment: 0 },
+                        })
+                        .insert(Name::from("Web string"));
+                });
+            web_counter.0 += 1;
+
+            if let Some(cooldown) = web_settings.shoot_cooldown {
+                abilities.web_cooldown_counter = cooldown;
+                evw_web.send(WebEvent::CooldownStarted {
+                    player,
+                    duration: cooldown,
+                });
             }
-        });
+        }
+    });
+}
+
+/// Wrap the web around terrain corners between the player and the current pivot, and unwrap the
+/// last bend once the player can see the pivot before it again.
+pub fn wrap_web_around_terrain(
+    mut q_web: Query<(&mut Web, &Children)>,
+    q_web_head: Query<&GlobalTransform, With<WebHead>>,
+    q_player: Query<&GlobalTransform, With<Player>>,
+    rapier_context: Res<RapierContext>,
+) {
+    if q_web_head.is_empty() || q_web.is_empty() {
+        return;
+    }
+
+    let filter = QueryFilter::new().exclude_sensors().groups(CollisionGroups::new(
+        GameCollisionGroups::WEB,
+        GameCollisionGroups::NON_STICK_TERRAIN | GameCollisionGroups::WEB_STICKABLE_TERRAIN,
+    ));
+    // Stop a bit short of the target so the terrain the web head is stuck in isn't hit
+    let terrain_between = |from: Vec2, to: Vec2| {
+        let distance = from.distance(to);
+        (distance > WEB_BEND_MARGIN)
+            .then(|| {
+                rapier_context.cast_ray_and_get_normal(
+                    from,
+                    (to - from) / distance,
+                    distance - WEB_BEND_MARGIN,
+                    true,
+                    filter,
+                )
+            })
+            .flatten()
+            .map(|(_, intersection)| intersection)
+    };
+
+    q_web.for_each_mut(|(mut web, children)| {
+        if !web.attached || web.is_bridge() {
+            return;
+        }
+        let Some(web_head_translation) = find_web_head_translation(children, &q_web_head) else {
+            return;
+        };
+        let Some(player_transform) = web.owner.and_then(|owner| q_player.get(owner).ok()) else {
+            return;
+        };
+        let player_translation = player_transform.translation().truncate();
+
+        while !web.bend_points.is_empty() {
+            let previous_pivot = web
+                .bend_points
+                .iter()
+                .rev()
+                .nth(1)
+                .copied()
+                .unwrap_or(web_head_translation);
+            if terrain_between(player_translation, previous_pivot).is_some() {
+                break;
+            }
+            web.bend_points.pop();
+        }
+
+        let pivot = web.pivot(web_head_translation);
+        if let Some(intersection) = terrain_between(player_translation, pivot) {
+            web.bend_points
+                .push(intersection.point + WEB_BEND_MARGIN * intersection.normal);
+        }
+    });
 }
 
 pub fn update_web_string_and_pull_force(
-    q_web: Query<&Web>,
-    mut q_web_string: Query<&mut Transform, With<WebString>>,
-    q_web_head: Query<&GlobalTransform, (With<WebHead>, Without<WebString>)>,
-    mut q_player: Query<(&GlobalTransform, &mut ComplexExternalForce, &mut Damping), With<Player>>,
+    q_web: Query<(Entity, &Web, &Children)>,
+    mut q_web_string: Query<(&WebString, &mut Transform)>,
+    q_web_string_visual: Query<(&Mesh2dHandle, &Handle<ColorMaterial>), With<WebString>>,
+    mut q_web_head: Query<
+        (&GlobalTransform, Option<&mut ImpulseJoint>),
+        (With<WebHead>, Without<WebString>),
+    >,
+    mut q_player: Query<
+        (&GlobalTransform, &mut ComplexExternalForce, &mut Damping),
+        With<Player>,
+    >,
+    mut commands: Commands,
 ) {
     if q_web_head.is_empty() || q_web_string.is_empty() {
         return;
     }
-    let web_head_translation = q_web_head.single().translation().truncate();
-    let (player_transform, mut cef, mut damping) = q_player.single_mut();
-    let player_translation = player_transform.translation().truncate();
-    let player_to_web_head = web_head_translation - player_translation;
-    let length = player_to_web_head.length();
-
-    let web = q_web.single();
-    let mut web_string = q_web_string.single_mut();
-    let (midpoint, angle) = midpoint_and_angle_to_x(player_translation, web_head_translation);
-    web_string.scale = Vec3::new(length, 0.2, 1.0);
-    web_string.translation = midpoint.extend(WEB_Z);
-    web_string.rotation = Quat::from_rotation_z(-angle);
-    web.attached.then(|| {
-        cef.forces
-            .entry(web.pull_force_id.unwrap())
-            .and_modify(|pull_dir| {
-                let initial_web_length = web.initial_web_length.unwrap();
+    let visual = q_web_string_visual.iter().next();
+
+    for (web_entity, web, children) in q_web.iter() {
+        let Some(web_head) = children.iter().find(|child| q_web_head.contains(**child)) else {
+            continue;
+        };
+        let Ok((web_head_transform, joint)) = q_web_head.get_mut(*web_head) else {
+            continue;
+        };
+        let web_head_translation = web_head_transform.translation().truncate();
+        let mut player = web.owner.and_then(|owner| q_player.get_mut(owner).ok());
+        let player_translation = player
+            .as_ref()
+            .map(|(transform, _, _)| transform.translation().truncate());
+
+        // One string segment between each pair of consecutive points, bridges end at their anchor
+        let Some(end) = web.anchor.or(player_translation) else {
+            continue;
+        };
+        let points: Vec<Vec2> = std::iter::once(web_head_translation)
+            .chain(web.bend_points.iter().copied())
+            .chain(std::iter::once(end))
+            .collect();
+        let segment_count = points.len() - 1;
+        let mut existing_segments = 0;
+        for child in children.iter() {
+            let Ok((web_string, mut transform)) = q_web_string.get_mut(*child) else {
+                continue;
+            };
+            if web_string.segment >= segment_count {
+                commands.entity(*child).despawn_recursive();
+                continue;
+            }
+            let (start, end) = (points[web_string.segment], points[web_string.segment + 1]);
+            let (midpoint, angle) = midpoint_and_angle_to_x(start, end);
+            transform.scale = Vec3::new(start.distance(end), 0.2, 1.0);
+            transform.translation = midpoint.extend(WEB_Z);
+            transform.rotation = Quat::from_rotation_z(-angle);
+            existing_segments = existing_segments.max(web_string.segment + 1);
+        }
+        if let Some((mesh, material)) = visual {
+            commands.entity(web_entity).with_children(|child_builder| {
+                for segment in existing_segments..segment_count {
+                    child_builder
+                        .spawn(WebStringBundle {
+                            web_string: WebString { segment },
+                            visual: MaterialMesh2dBundle {
+                                mesh: mesh.clone(),
+                                material: material.clone(),
+                                transform: Transform::from_scale(Vec3::new(0.0, 0.2, 1.0)),
+                                ..default()
+                            },
+                        })
+                        .insert(Name::from("Web string"));
+                }
+            });
+        }
+
+        let (true, false, Some(web_length), Some((player_transform, cef, damping))) = (
+            web.attached,
+            web.is_bridge(),
+            web.initial_web_length,
+            player.as_mut(),
+        ) else {
+            continue;
+        };
+        // Only the part of the web past the last bend is free to swing
+        let pivot = web.pivot(web_head_translation);
+        let free_length = (web_length - web.wrapped_length(web_head_translation)).max(0.0);
+        let player_to_pivot = pivot - player_transform.translation().truncate();
+
+        if let Some(pull_force) = web.pull_force {
+            cef.modify(pull_force, |pull_dir| {
                 *pull_dir = WEB_PULL_FORCE_SCALE
-                    * (player_to_web_head.length() - initial_web_length).clamp(0.0, f32::MAX)
-                    * player_to_web_head.normalize();
+                    * (player_to_pivot.length() - free_length).clamp(0.0, f32::MAX)
+                    * player_to_pivot.normalize_or_zero();
                 (pull_dir.length() > 0.0).then(|| {
                     damping.linear_damping = 0.5;
                 });
             });
+        }
+
+        // The web head doesn't rotate so the pivot offset is already in its local space
+        if let Some(mut joint) = joint {
+            let anchor = pivot - web_head_translation;
+            let limit_changed = joint
+                .data
+                .limits(JointAxis::X)
+                .map_or(true, |limits| limits.max != free_length);
+            (joint.data.local_anchor2() != anchor || limit_changed).then(|| {
+                joint
+                    .data
+                    .set_local_anchor2(anchor)
+                    .set_limits(JointAxis::X, [0.0, free_length])
+                    .set_limits(JointAxis::Y, [0.0, free_length]);
+            });
+        }
+    }
+}
+
+/// Shorten or lengthen attached webs over time, climbing toward or lowering away from the anchor.
+/// Reeling shares its keys with crawling up and down, so crawling players don't reel.
+pub fn handle_web_reel(
+    q_player: Query<(&PlayerInput, &PlayerInfo, &PlayerAbilities), With<Player>>,
+    web_settings: Res<WebSettings>,
+    mut q_web: Query<&mut Web>,
+) {
+    q_web.for_each_mut(|mut web| {
+        let (true, false, Some(web_length), Some((input, info, abilities))) = (
+            web.attached,
+            web.is_bridge(),
+            web.initial_web_length,
+            web.owner.and_then(|owner| q_player.get(owner).ok()),
+        ) else {
+            return;
+        };
+        if info.is_crawling {
+            return;
+        }
+        let reel_axis = input.reel_out.then_some(1.0).unwrap_or(0.0)
+            - input.reel_in.then_some(1.0).unwrap_or(0.0);
+        if reel_axis == 0.0 {
+            return;
+        }
+        let max_length = web_settings.rope_length_limit(abilities);
+        let web_length = (web_length + reel_axis * web_settings.reel_speed * FIXED_TIMESTEP)
+            .clamp(web_settings.min_rope_length, max_length);
+        web.initial_web_length = Some(web_length);
     });
 }
 
+/// Despawn webs that flew past [`WebSettings::max_range`] without sticking to anything
+pub fn limit_web_range(
+    q_web: Query<(Entity, &Web, &Children)>,
+    q_web_head: Query<&GlobalTransform, With<WebHead>>,
+    q_player: Query<(&GlobalTransform, &PlayerAbilities), With<Player>>,
+    web_settings: Res<WebSettings>,
+    mut evw_despawn_web: EventWriter<DespawnWebEvent>,
+) {
+    if q_web.is_empty() || q_web_head.is_empty() {
+        return;
+    }
+    q_web
+        .iter()
+        .filter(|(_, web, _)| !web.attached)
+        .filter(|(_, web, children)| {
+            // Webs whose player is gone have nothing to be in range of
+            let Some((player_transform, abilities)) =
+                web.owner.and_then(|owner| q_player.get(owner).ok())
+            else {
+                return true;
+            };
+            let max_range = web_settings.max_range * abilities.range_scale();
+            find_web_head_translation(children, &q_web_head).map_or(false, |translation| {
+                translation.distance(player_transform.translation().truncate()) > max_range
+            })
+        })
+        .for_each(|(entity, _, _)| evw_despawn_web.send(DespawnWebEvent::Web(entity)));
+}
+
 pub fn handle_web_head_collision(
-    mut q_web_head: Query<(Entity, &mut Velocity, &GlobalTransform), With<WebHead>>,
-    mut q_web: Query<(Entity, &mut Web)>,
+    mut q_web_head: Query<(&mut Velocity, &GlobalTransform, &Parent), With<WebHead>>,
+    mut q_web: Query<&mut Web>,
     q_web_stickable: Query<Entity, With<WebStickable>>,
-    mut q_player: Query<(&GlobalTransform, &mut PlayerMovement), With<Player>>,
-    mut q_cef: Query<&mut ComplexExternalForce, With<Player>>,
+    q_web_grabbable: Query<&GlobalTransform, With<WebGrabbable>>,
+    q_moving_body: Query<&GlobalTransform, With<MovingBody>>,
+    mut q_player: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &PlayerAbilities,
+            &mut PlayerMovement,
+            &mut ComplexExternalForce,
+        ),
+        With<Player>,
+    >,
+    q_parents: Query<&Parent>,
+    web_settings: Res<WebSettings>,
     mut evr_collisions: EventReader<CollisionEvent>,
+    mut evw_grab: EventWriter<WebGrabEvent>,
+    mut evw_web: EventWriter<WebEvent>,
     mut commands: Commands,
 ) {
     if evr_collisions.is_empty() || q_web_head.is_empty() {
         return;
     }
-    let (web_head, mut web_head_vel, web_head_transform) = q_web_head.single_mut();
-    let (player_transform, mut player_movement) = q_player.single_mut();
     for collision in evr_collisions.iter() {
         if let CollisionEvent::Started(entity_one, entity_two, _) = collision {
-            let other_entity = (web_head == *entity_one)
-                .then_some(*entity_two)
-                .or_else(|| (web_head == *entity_two).then_some(*entity_one));
-            if let None = other_entity {
+            let Some((web_head, other_entity)) = q_web_head
+                .contains(*entity_one)
+                .then_some((*entity_one, *entity_two))
+                .or_else(|| {
+                    q_web_head
+                        .contains(*entity_two)
+                        .then_some((*entity_two, *entity_one))
+                })
+            else {
+                continue;
+            };
+            let Ok((mut web_head_vel, web_head_transform, parent)) = q_web_head.get_mut(web_head)
+            else {
+                continue;
+            };
+            let web_entity = parent.get();
+            let Ok(mut web) = q_web.get_mut(web_entity) else {
+                continue;
+            };
+            if web.attached {
+                continue;
+            }
+            // Another player's body tethers the two of them, the shooter's own body is ignored
+            let tethered = player_of_collider(&q_parents, |e| q_player.contains(e), other_entity)
+                .and_then(|other| q_player.get(other).ok())
+                .map(|(other, transform, ..)| (other, transform.translation().truncate()));
+            if tethered.map_or(false, |(other, _)| web.owner == Some(other)) {
+                continue;
+            }
+            let Some(Ok((player, player_transform, abilities, mut player_movement, mut cef))) =
+                web.owner.map(|owner| q_player.get_mut(owner))
+            else {
+                commands.entity(web_entity).despawn_recursive();
+                continue;
+            };
+            // Webs landing further away than the longest rope don't hold, shortening them here
+            // would yank the player toward the web head
+            let landing_distance = web_head_transform
+                .translation()
+                .truncate()
+                .distance(player_transform.translation().truncate());
+            if landing_distance > web_settings.rope_length_limit(abilities) {
+                commands.entity(web_entity).despawn_recursive();
+                continue;
+            }
+
+            // Tethered players pull on each other with the spring force no matter the web physics,
+            // the other end is pulled by [`super::web_tether::pull_tethered_players`]
+            if let Some((other, other_translation)) = tethered {
+                web_head_vel.linvel = Vec2::ZERO;
+                let pull_dir = other_translation - player_transform.translation().truncate();
+
+                web.attached = true;
+                web.initial_web_length = Some(pull_dir.length());
+                web.pull_force = Some(cef.register("web pull"));
+                web.tethered = Some(other);
+                player_movement.airborne_acceleration = PlayerMovement::SWINGING_AIR_ACCEL;
+                evw_web.send(WebEvent::Tethered {
+                    web: web_entity,
+                    player: other,
+                });
+                continue;
+            }
+
+            // Grabbed objects are pulled with the spring force no matter the web physics, the
+            // tension is shared between the player and the object by
+            // [`super::web_grab::pull_web_grabbed_objects`]
+            if let Ok(object_transform) = q_web_grabbable.get(other_entity) {
+                web_head_vel.linvel = Vec2::ZERO;
+                let web_head_translation = web_head_transform.translation();
+                let pull_dir = web_head_translation.truncate()
+                    - player_transform.translation().truncate();
+                let pull_force = cef.register("web pull");
+
+                web.attached = true;
+                web.initial_web_length = Some(pull_dir.length());
+                web.pull_force = Some(pull_force);
+                web.grabbed = Some(other_entity);
+                web.grab_offset = object_transform
+                    .affine()
+                    .inverse()
+                    .transform_point3(web_head_translation);
+                player_movement.airborne_acceleration = PlayerMovement::SWINGING_AIR_ACCEL;
+                evw_grab.send(WebGrabEvent::Grabbed {
+                    web: web_entity,
+                    object: other_entity,
+                });
                 continue;
             }
-            let other_entity = other_entity.unwrap();
 
             q_web_stickable
                 .get(other_entity)
@@ -202,22 +571,43 @@ pub fn handle_web_head_collision(
                     web_head_vel.linvel = Vec2::ZERO;
                     // web swinging starts
                     //
-                    let mut cef = q_cef.single_mut();
-                    let next_force_id = cef.next_force();
                     let pull_dir = web_head_transform.translation().truncate()
                         - player_transform.translation().truncate();
-                    cef.forces.insert(next_force_id, pull_dir.normalize());
+                    let web_length = pull_dir.length();
 
-                    let (_, mut web) = q_web.single_mut();
                     web.attached = true;
-                    web.pull_force_id = Some(next_force_id);
-                    web.initial_web_length = Some(pull_dir.length());
+                    web.initial_web_length = Some(web_length);
+                    // Webs stuck to a moving platform are carried along by
+                    // [`super::web_grab::follow_moving_platforms`]
+                    if let Ok(platform_transform) = q_moving_body.get(other_entity) {
+                        web.platform = Some(other_entity);
+                        web.grab_offset = platform_transform
+                            .affine()
+                            .inverse()
+                            .transform_point3(web_head_transform.translation());
+                    }
+                    match web_settings.physics {
+                        WebPhysics::Spring => {
+                            let pull_force = cef.register("web pull");
+                            cef.set(pull_force, pull_dir.normalize());
+                            web.pull_force = Some(pull_force);
+                        }
+                        WebPhysics::Rope => {
+                            // The joint lives on the web head so each web holds the player
+                            // with its own rope and is removed along with it
+                            commands.entity(web_head).insert((
+                                RigidBody::Fixed,
+                                ImpulseJoint::new(
+                                    player,
+                                    RopeJointBuilder::new().limits([0.0, web_length]),
+                                ),
+                            ));
+                        }
+                    }
                     player_movement.airborne_acceleration = PlayerMovement::SWINGING_AIR_ACCEL;
                 })
                 .unwrap_or_else(|| {
-                    q_web.for_each(|(entity, _)| {
-                        commands.entity(entity).despawn_recursive();
-                    });
+                    commands.entity(web_entity).despawn_recursive();
                 });
         }
     }
@@ -226,38 +616,98 @@ pub fn handle_web_head_collision(
 pub fn despawn_web(
     q_web: Query<(Entity, &Web)>,
     mut q_cef_movement: Query<(&mut ComplexExternalForce, &mut PlayerMovement), With<Player>>,
-    evr_despawn_web: EventReader<DespawnWebEvent>,
+    mut evr_despawn_web: EventReader<DespawnWebEvent>,
+    mut evw_grab: EventWriter<WebGrabEvent>,
     mut commands: Commands,
 ) {
     if evr_despawn_web.is_empty() || q_web.is_empty() {
         return;
     }
-    evr_despawn_web.clear();
-    let (web_entity, web) = q_web.single();
-    let (mut cef, mut movement) = q_cef_movement.single_mut();
-    if let Some(pull_force_id) = web.pull_force_id {
-        cef.forces.remove(&pull_force_id);
-    }
-    movement.airborne_acceleration = PlayerMovement::NORM_AIR_ACCEL;
-    commands.entity(web_entity).despawn_recursive();
+    let from_player = |player: Entity| {
+        q_web
+            .iter()
+            .filter(move |(_, web)| web.owner == Some(player) && !web.is_bridge())
+    };
+    let despawned: HashSet<Entity> = evr_despawn_web
+        .iter()
+        .flat_map(|ev| match ev {
+            DespawnWebEvent::Newest(player) => from_player(*player)
+                .max_by_key(|(_, web)| web.id)
+                .map(|(entity, _)| entity)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            DespawnWebEvent::AllFromPlayer(player) => {
+                from_player(*player).map(|(entity, _)| entity).collect()
+            }
+            DespawnWebEvent::Web(entity) => vec![*entity],
+        })
+        .collect();
+
+    let mut owners = HashSet::new();
+    despawned
+        .iter()
+        .filter_map(|entity| q_web.get(*entity).ok())
+        .for_each(|(entity, web)| {
+            let owner = web
+                .owner
+                .and_then(|owner| q_cef_movement.get_mut(owner).ok());
+            if let (Some(pull_force), Some((mut cef, _))) = (web.pull_force, owner) {
+                cef.unregister(pull_force);
+            }
+            let tethered = web
+                .tethered
+                .and_then(|tethered| q_cef_movement.get_mut(tethered).ok());
+            if let (Some(tether_force), Some((mut cef, _))) = (web.tether_force, tethered) {
+                cef.unregister(tether_force);
+            }
+            if let Some(object) = web.grabbed {
+                evw_grab.send(WebGrabEvent::Released {
+                    web: entity,
+                    object,
+                });
+            }
+            owners.extend(web.owner);
+            commands.entity(entity).despawn_recursive();
+        });
+
+    owners.into_iter().for_each(|player| {
+        let still_swinging =
+            from_player(player).any(|(entity, web)| web.attached && !despawned.contains(&entity));
+        if let (false, Ok((_, mut movement))) = (still_swinging, q_cef_movement.get_mut(player)) {
+            movement.airborne_acceleration = PlayerMovement::NORM_AIR_ACCEL;
+        }
+    });
 }
 
 pub fn despawn_web_on_player_death(
+    q_web: Query<(Entity, &Web)>,
     mut evr_death: EventReader<PlayerEvent>,
     mut evw_despawn_web: EventWriter<DespawnWebEvent>,
 ) {
-    evr_death
+    evr_death.iter().for_each(|ev| {
+        if let PlayerEvent::Died(player) = ev {
+            evw_despawn_web.send(DespawnWebEvent::AllFromPlayer(*player));
+            // Other players' tethers let go of the respawning player
+            q_web
+                .iter()
+                .filter(|(_, web)| web.tethered == Some(*player))
+                .for_each(|(entity, _)| evw_despawn_web.send(DespawnWebEvent::Web(entity)));
+        }
+    });
+}
+
+/// Translation of the web head among the children of a web
+pub fn find_web_head_translation(
+    children: &Children,
+    q_web_head: &Query<&GlobalTransform, With<WebHead>>,
+) -> Option<Vec2> {
+    children
         .iter()
-        .any(|ev| match ev {
-            PlayerEvent::Died(_) => true,
-            _ => false,
-        })
-        .then(|| {
-            evw_despawn_web.send(DespawnWebEvent);
-        });
+        .find_map(|child| q_web_head.get(*child).ok())
+        .map(|transform| transform.translation().truncate())
 }
 
-fn midpoint_and_angle_to_x(start: Vec2, end: Vec2) -> (Vec2, f32) {
+pub fn midpoint_and_angle_to_x(start: Vec2, end: Vec2) -> (Vec2, f32) {
     let midpoint = (start + end) / 2.0;
     let angle = (end - start).angle_between(Vec2::X);
     (midpoint, angle)
@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    data::{
+        player::{Player, PlayerInfo, PlayerJump, PlayerMovement, PlayerWall, PlayerWallJump},
+        tilemap::{Platform, WebStickable},
+    },
+    plugins::physics::FIXED_TIMESTEP,
+};
+
+use super::PlayerEvent;
+
+pub fn check_if_on_wall(
+    q_child: Query<&Children>,
+    q_wall: Query<&PlayerWall, With<Sensor>>,
+    q_platform: Query<&Collider, With<Platform>>,
+    q_web_stickable: Query<Entity, With<WebStickable>>,
+    mut q_player: Query<(Entity, &mut PlayerInfo), With<Player>>,
+    rapier_context: Res<RapierContext>,
+) {
+    let touched_wall = |child: Entity| {
+        q_wall.get(child).ok().and_then(|wall| {
+            rapier_context
+                .intersections_with(child)
+                .filter(|(_, _, intersecting)| *intersecting)
+                .map(|(entity1, entity2, _)| {
+                    (entity1 == child).then_some(entity2).unwrap_or(entity1)
+                })
+                .find(|other| q_platform.contains(*other))
+                .map(|other| (wall.side, q_web_stickable.contains(other)))
+        })
+    };
+
+    q_player.for_each_mut(|(player, mut player_info)| {
+        let (wall_side, is_wall_stickable) = q_child
+            .iter_descendants(player)
+            .find_map(&touched_wall)
+            .unwrap_or((0.0, false));
+        player_info.wall_side = wall_side;
+        player_info.is_wall_stickable = is_wall_stickable;
+    });
+}
+
+/// Reduce gravity while the player is falling against a wall. Web stickable terrain is clung to
+/// without holding toward it while ordinary terrain needs the player to push into the wall.
+pub fn handle_wall_slide(
+    mut q_player: Query<
+        (
+            Entity,
+            &mut PlayerInfo,
+            &PlayerWallJump,
+            &PlayerMovement,
+            &mut Velocity,
+            &mut GravityScale,
+        ),
+        With<Player>,
+    >,
+    mut e_writer: EventWriter<PlayerEvent>,
+) {
+    q_player.for_each_mut(
+        |(entity, mut info, wall_jump, movement, mut vel, mut gravity_scale)| {
+            if info.is_crawling {
+                return;
+            }
+            let pushing_wall = info.wall_side != 0.0 && movement.axis == info.wall_side;
+            let sliding = !info.is_grounded
+                && info.wall_side != 0.0
+                && vel.linvel.y <= 0.0
+                && (info.is_wall_stickable || pushing_wall);
+
+            let (slide_gravity_scale, slide_max_velocity) = info
+                .is_wall_stickable
+                .then_some((wall_jump.cling_gravity_scale, wall_jump.cling_max_velocity))
+                .unwrap_or((wall_jump.slide_gravity_scale, wall_jump.slide_max_velocity));
+
+            sliding
+                .then(|| {
+                    gravity_scale.0 = slide_gravity_scale;
+                    vel.linvel.y = vel.linvel.y.max(-slide_max_velocity);
+                })
+                .unwrap_or_else(|| gravity_scale.0 = wall_jump.normal_gravity_scale);
+
+            (sliding != info.is_wall_sliding).then(|| {
+                e_writer.send(
+                    sliding
+                        .then_some(PlayerEvent::WallSliding(entity))
+                        .unwrap_or(PlayerEvent::WallReleased(entity)),
+                );
+            });
+            info.is_wall_sliding = sliding;
+        },
+    );
+}
+
+/// Jump away from the wall using the buffered jump input from [`super::jump::handle_jump`]
+pub fn handle_wall_jump(
+    mut q_player: Query<
+        (
+            Entity,
+            &PlayerInfo,
+            &mut PlayerJump,
+            &mut PlayerWallJump,
+            &mut Velocity,
+            &mut ExternalImpulse,
+        ),
+        With<Player>,
+    >,
+    mut e_writer: EventWriter<PlayerEvent>,
+) {
+    q_player.for_each_mut(
+        |(entity, info, mut jump, mut wall_jump, mut vel, mut impulse)| {
+            wall_jump.control_lock_counter =
+                (wall_jump.control_lock_counter - FIXED_TIMESTEP).max(0.0);
+
+            let can_wall_jump = !info.is_grounded
+                && !info.is_crawling
+                && info.wall_side != 0.0
+                && jump.coyote_counter <= 0.0
+                && jump.buffer_counter > 0.0;
+            can_wall_jump.then(|| {
+                vel.linvel = Vec2::ZERO;
+                impulse.impulse = Vec2::new(
+                    -info.wall_side * wall_jump.impulse.x,
+                    wall_jump.impulse.y,
+                );
+                jump.buffer_counter = 0.0;
+                jump.is_jumping = true;
+                wall_jump.control_lock_counter = wall_jump.control_lock_time;
+
+                e_writer.send(PlayerEvent::WallJumped(entity));
+            });
+        },
+    );
+}
@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::data::{
+    physics::{CollisionGroupsFilter, ComplexExternalForce, GameCollisionGroups},
+    player::{Player, PlayerAbilities, PlayerMovement},
+    tilemap::WebStickable,
+    web::{Web, WebBridgeBundle, WebHead},
+};
+
+use super::{
+    aim::resolve_aim,
+    shoot_web::{find_web_head_translation, midpoint_and_angle_to_x},
+    PlayerInput, WebSettings,
+};
+
+/// Turn the newest attached web into a bridge by sticking its player end to the web stickable
+/// surface under the cursor. The web stops holding the player and becomes a solid platform.
+pub fn build_web_bridge(
+    web_settings: Res<WebSettings>,
+    rapier_context: Res<RapierContext>,
+    q_web_stickable: Query<Entity, With<WebStickable>>,
+    q_web_head: Query<&GlobalTransform, With<WebHead>>,
+    mut q_web: Query<(Entity, &mut Web, &Children)>,
+    mut q_player: Query<
+        (
+            Entity,
+            &PlayerInput,
+            &GlobalTransform,
+            &PlayerAbilities,
+            &mut ComplexExternalForce,
+            &mut PlayerMovement,
+        ),
+        With<Player>,
+    >,
+    mut commands: Commands,
+) {
+    q_player.for_each_mut(
+        |(player, input, player_transform, abilities, mut cef, mut movement)| {
+            if !input.bridge_pressed {
+                return;
+            }
+            let swinging = |web: &Web| {
+                web.owner == Some(player)
+                    && web.attached
+                    && !web.is_bridge()
+                    && web.grabbed.is_none()
+                    && web.tethered.is_none()
+            };
+            let Some(web_entity) = q_web
+                .iter()
+                .filter(|(_, web, _)| swinging(web))
+                .max_by_key(|(_, web, _)| web.id)
+                .map(|(entity, _, _)| entity)
+            else {
+                return;
+            };
+            let still_swinging = q_web
+                .iter()
+                .any(|(entity, web, _)| entity != web_entity && swinging(web));
+
+            let player_translation = player_transform.translation().truncate();
+            let Some(anchor) = input
+                .cursor
+                .and_then(|cursor| {
+                    resolve_aim(
+                        player,
+                        player_translation,
+                        cursor,
+                        web_settings.reach(abilities),
+                        &web_settings,
+                        &rapier_context,
+                        |entity| q_web_stickable.contains(entity),
+                    )
+                })
+                .filter(|aim| aim.will_stick)
+                .and_then(|aim| aim.hit)
+            else {
+                return;
+            };
+
+            let Ok((_, mut web, children)) = q_web.get_mut(web_entity) else {
+                return;
+            };
+            let Some(web_head_translation) = find_web_head_translation(children, &q_web_head)
+            else {
+                return;
+            };
+
+            // Let go of the player, the rope joint goes away with the web head's
+            if let Some(pull_force) = web.pull_force.take() {
+                cef.unregister(pull_force);
+            }
+            children
+                .iter()
+                .filter(|child| q_web_head.contains(**child))
+                .for_each(|web_head| {
+                    commands.entity(*web_head).remove::<ImpulseJoint>();
+                });
+            (!still_swinging).then(|| {
+                movement.airborne_acceleration = PlayerMovement::NORM_AIR_ACCEL;
+            });
+
+            web.anchor = Some(anchor);
+            web.bend_points.clear();
+
+            let (midpoint, angle) = midpoint_and_angle_to_x(web_head_translation, anchor);
+            commands.entity(web_entity).with_children(|child_builder| {
+                child_builder
+                    .spawn(WebBridgeBundle {
+                        transform: TransformBundle::from_transform(Transform {
+                            translation: midpoint.extend(0.0),
+                            rotation: Quat::from_rotation_z(-angle),
+                            ..default()
+                        }),
+                        collider: Collider::cuboid(
+                            web_head_translation.distance(anchor) / 2.0,
+                            web_settings.bridge_thickness / 2.0,
+                        ),
+                        rigid_body: RigidBody::Fixed,
+                        restitution: Restitution {
+                            coefficient: web_settings.bridge_bounciness,
+                            combine_rule: CoefficientCombineRule::Max,
+                        },
+                        collision_groups: CollisionGroups {
+                            memberships: GameCollisionGroups::NON_STICK_TERRAIN,
+                            filters: GameCollisionGroups::NON_STICK_TERRAIN.filter_group(),
+                        },
+                        ..default()
+                    })
+                    .insert(Name::from("Web bridge"));
+            });
+        },
+    );
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::data::{
+    physics::ComplexExternalForce,
+    platform::MovingBody,
+    player::Player,
+    tilemap::{WebGrabbable, WebSwitch},
+    web::{Web, WebHead},
+};
+
+use super::{
+    shoot_web::{DespawnWebEvent, WEB_PULL_FORCE_SCALE},
+    WebGrabEvent,
+};
+
+/// Keep web heads stuck to the objects they grabbed and pull those objects toward the player.
+/// The object gets the same tension the player feels, so the heavier of the two moves less.
+pub fn pull_web_grabbed_objects(
+    q_web: Query<(Entity, &Web, &Children)>,
+    mut q_web_head: Query<&mut Transform, With<WebHead>>,
+    mut q_grabbable: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            Option<&mut ComplexExternalForce>,
+            &mut WebGrabbable,
+            Option<&mut WebSwitch>,
+        ),
+    >,
+    q_player: Query<&GlobalTransform, With<Player>>,
+    mut evw_despawn_web: EventWriter<DespawnWebEvent>,
+    mut evw_grab: EventWriter<WebGrabEvent>,
+) {
+    if q_grabbable.is_empty() {
+        return;
+    }
+
+    let mut tensions: HashMap<Entity, Vec2> = HashMap::new();
+    for (web_entity, web, children) in q_web.iter() {
+        let (Some(object), Some(web_length)) = (web.grabbed, web.initial_web_length) else {
+            continue;
+        };
+        let (Ok((_, object_transform, _, _, _)), Some(Ok(player_transform))) = (
+            q_grabbable.get(object),
+            web.owner.map(|owner| q_player.get(owner)),
+        ) else {
+            // The object or the player is gone, e.g. the object's level was unloaded
+            evw_despawn_web.send(DespawnWebEvent::Web(web_entity));
+            continue;
+        };
+        let player_translation = player_transform.translation().truncate();
+
+        let web_head_translation = object_transform
+            .affine()
+            .transform_point3(web.grab_offset)
+            .truncate();
+        children.iter().for_each(|child| {
+            if let Ok(mut transform) = q_web_head.get_mut(*child) {
+                transform.translation = web_head_translation.extend(transform.translation.z);
+            }
+        });
+
+        let pivot = web.pivot(web_head_translation);
+        let stretch = web.wrapped_length(web_head_translation) + pivot.distance(player_translation)
+            - web_length;
+        let toward_player = web
+            .bend_points
+            .first()
+            .copied()
+            .unwrap_or(player_translation)
+            - web_head_translation;
+        *tensions.entry(object).or_default() +=
+            WEB_PULL_FORCE_SCALE * stretch.max(0.0) * toward_player.normalize_or_zero();
+    }
+
+    q_grabbable.for_each_mut(|(entity, _, cef, mut grabbable, switch)| {
+        let tension = tensions.get(&entity).copied().unwrap_or(Vec2::ZERO);
+        if let Some(mut cef) = cef {
+            let pull_force = grabbable
+                .pull_force
+                .filter(|pull_force| cef.is_registered(*pull_force))
+                .unwrap_or_else(|| cef.register("web tension"));
+            grabbable.pull_force = Some(pull_force);
+            cef.set(pull_force, tension);
+        }
+
+        // Flip once per pull, the tension has to drop before the switch can flip again
+        if let Some(mut switch) = switch {
+            let is_pulled = tension.length() >= switch.flip_tension;
+            (is_pulled && !switch.is_pulled).then(|| {
+                switch.is_on = !switch.is_on;
+                evw_grab.send(WebGrabEvent::SwitchFlipped {
+                    switch: entity,
+                    is_on: switch.is_on,
+                });
+            });
+            switch.is_pulled = is_pulled;
+        }
+    });
+}
+
+/// Keep web heads stuck to the moving platforms they hit, the web stays as long as it was so the
+/// player swings along with the platform
+pub fn follow_moving_platforms(
+    q_web: Query<(Entity, &Web, &Children)>,
+    mut q_web_head: Query<&mut Transform, With<WebHead>>,
+    q_platform: Query<&GlobalTransform, With<MovingBody>>,
+    mut evw_despawn_web: EventWriter<DespawnWebEvent>,
+) {
+    q_web.for_each(|(web_entity, web, children)| {
+        let Some(platform) = web.platform else {
+            return;
+        };
+        let Ok(platform_transform) = q_platform.get(platform) else {
+            // The platform's level was unloaded
+            evw_despawn_web.send(DespawnWebEvent::Web(web_entity));
+            return;
+        };
+
+        let web_head_translation = platform_transform
+            .affine()
+            .transform_point3(web.grab_offset)
+            .truncate();
+        children.iter().for_each(|child| {
+            if let Ok(mut transform) = q_web_head.get_mut(*child) {
+                transform.translation = web_head_translation.extend(transform.translation.z);
+            }
+        });
+    });
+}
@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    data::{
+        player::{Player, PlayerAbilities},
+        tilemap::WebUpgradePickup,
+        web::{Web, WebHead},
+    },
+    plugins::physics::FIXED_TIMESTEP,
+};
+
+use super::{
+    shoot_web::{find_web_head_translation, DespawnWebEvent, WEB_PULL_FORCE_SCALE},
+    PlayerEvent, WebEvent, WebSettings,
+};
+
+pub fn update_web_cooldown(
+    mut q_player: Query<(Entity, &mut PlayerAbilities), With<Player>>,
+    mut evw_web: EventWriter<WebEvent>,
+) {
+    q_player.for_each_mut(|(player, mut abilities)| {
+        if abilities.web_cooldown_counter <= 0.0 {
+            return;
+        }
+        abilities.web_cooldown_counter =
+            (abilities.web_cooldown_counter - FIXED_TIMESTEP).max(0.0);
+        (abilities.web_cooldown_counter == 0.0).then(|| {
+            evw_web.send(WebEvent::CooldownFinished(player));
+        });
+    });
+}
+
+/// Snap attached webs that are past [`WebSettings::max_attached_time`] or
+/// [`WebSettings::snap_tension`]. Tension is the centripetal acceleration keeping the player on
+/// its circle around the pivot plus the pull of a web stretched past its length.
+pub fn snap_webs(
+    web_settings: Res<WebSettings>,
+    mut q_web: Query<(Entity, &mut Web, &Children)>,
+    q_web_head: Query<&GlobalTransform, With<WebHead>>,
+    q_player: Query<(&GlobalTransform, &Velocity), With<Player>>,
+    mut evw_despawn_web: EventWriter<DespawnWebEvent>,
+    mut evw_web: EventWriter<WebEvent>,
+) {
+    if q_web.is_empty() {
+        return;
+    }
+
+    q_web.for_each_mut(|(entity, mut web, children)| {
+        let (true, false, Some(web_length), Some(Ok((player_transform, player_vel)))) = (
+            web.attached,
+            web.is_bridge(),
+            web.initial_web_length,
+            web.owner.map(|owner| q_player.get(owner)),
+        ) else {
+            return;
+        };
+        let player_translation = player_transform.translation().truncate();
+        web.attached_time += FIXED_TIMESTEP;
+
+        let too_old = web_settings
+            .max_attached_time
+            .map_or(false, |max_time| web.attached_time > max_time);
+        let too_tense = web_settings.snap_tension.map_or(false, |snap_tension| {
+            find_web_head_translation(children, &q_web_head).map_or(false, |head| {
+                let pivot = web.pivot(head);
+                let free_length = (web_length - web.wrapped_length(head)).max(0.0);
+                let to_player = player_translation - pivot;
+                let distance = to_player.length().max(f32::EPSILON);
+                let radial = to_player / distance;
+                let tangential_vel = player_vel.linvel - player_vel.linvel.dot(radial) * radial;
+                let tension = tangential_vel.length_squared() / distance
+                    + WEB_PULL_FORCE_SCALE * (distance - free_length).max(0.0);
+                tension > snap_tension
+            })
+        });
+
+        (too_old || too_tense).then(|| {
+            evw_despawn_web.send(DespawnWebEvent::Web(entity));
+            evw_web.send(WebEvent::Snapped(entity));
+        });
+    });
+}
+
+pub fn collect_web_upgrades(
+    q_pickup: Query<&WebUpgradePickup>,
+    mut q_player: Query<&mut PlayerAbilities, With<Player>>,
+    mut evr_player_collisions: EventReader<PlayerEvent>,
+    mut evw_web: EventWriter<WebEvent>,
+    mut commands: Commands,
+) {
+    if q_pickup.is_empty() {
+        return;
+    }
+    for ev in evr_player_collisions.iter() {
+        let PlayerEvent::Collided(player, entity) = ev else {
+            continue;
+        };
+        let (Ok(pickup), Ok(mut abilities)) = (q_pickup.get(*entity), q_player.get_mut(*player))
+        else {
+            continue;
+        };
+        abilities.unlock(pickup.upgrade).then(|| {
+            evw_web.send(WebEvent::UpgradeUnlocked(*player, pickup.upgrade));
+        });
+        commands.entity(*entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+
+use crate::data::{
+    physics::ComplexExternalForce,
+    player::Player,
+    web::{Web, WebHead},
+};
+
+use super::shoot_web::{DespawnWebEvent, WEB_PULL_FORCE_SCALE};
+
+/// Keep web heads stuck to the players they tethered and pull those players toward the shooter.
+/// The tethered player gets the same tension the shooter feels, so swinging on one end swings
+/// the other.
+pub fn pull_tethered_players(
+    mut q_web: Query<(Entity, &mut Web, &Children)>,
+    mut q_web_head: Query<&mut Transform, With<WebHead>>,
+    mut q_player: Query<(&GlobalTransform, &mut ComplexExternalForce), With<Player>>,
+    mut evw_despawn_web: EventWriter<DespawnWebEvent>,
+) {
+    q_web.for_each_mut(|(web_entity, mut web, children)| {
+        let (Some(tethered), Some(web_length)) = (web.tethered, web.initial_web_length) else {
+            return;
+        };
+        let owner_translation = web
+            .owner
+            .and_then(|owner| q_player.get(owner).ok())
+            .map(|(transform, _)| transform.translation().truncate());
+        let (Some(player_translation), Ok((tethered_transform, mut cef))) =
+            (owner_translation, q_player.get_mut(tethered))
+        else {
+            // One of the players is gone
+            evw_despawn_web.send(DespawnWebEvent::Web(web_entity));
+            return;
+        };
+
+        let web_head_translation = tethered_transform.translation().truncate();
+        children.iter().for_each(|child| {
+            if let Ok(mut transform) = q_web_head.get_mut(*child) {
+                transform.translation = web_head_translation.extend(transform.translation.z);
+            }
+        });
+
+        let pivot = web.pivot(web_head_translation);
+        let stretch = web.wrapped_length(web_head_translation) + pivot.distance(player_translation)
+            - web_length;
+        let toward_player = web
+            .bend_points
+            .first()
+            .copied()
+            .unwrap_or(player_translation)
+            - web_head_translation;
+        let tether_force = web
+            .tether_force
+            .filter(|tether_force| cef.is_registered(*tether_force))
+            .unwrap_or_else(|| cef.register("web tether"));
+        web.tether_force = Some(tether_force);
+        cef.set(
+            tether_force,
+            WEB_PULL_FORCE_SCALE * stretch.max(0.0) * toward_player.normalize_or_zero(),
+        );
+    });
+}
@@ -0,0 +1,282 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::Velocity;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    data::player::Player,
+    plugins::{
+        physics::FixedUpdateStage,
+        player::{lifecycle::translation_in_level, PlayerInput},
+        tilemap,
+    },
+    utils::state_helper::StateExtend,
+    GameState,
+};
+
+mod playback;
+mod record;
+
+const RECORD_ARG: &str = "--record";
+const REPLAY_ARG: &str = "--replay";
+const HEADLESS_ARG: &str = "--headless";
+/// Replays fail when the player ends further than this from where the recording ended
+pub const FINAL_POSITION_TOLERANCE: f32 = 0.5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// Write the input of the run to the file when the game closes
+    Record(PathBuf),
+    /// Feed the input from the file to the player instead of the keyboard and mouse, then check
+    /// that the player ended up where the recording did
+    Replay { path: PathBuf, headless: bool },
+}
+impl ReplayMode {
+    /// Read `--record <file>` or `--replay <file> [--headless]` from the command line
+    pub fn from_args() -> Option<Self> {
+        let args: Vec<String> = std::env::args().collect();
+        let path_after = |flag: &str| {
+            args.iter()
+                .position(|arg| arg == flag)
+                .and_then(|index| args.get(index + 1))
+                .map(PathBuf::from)
+        };
+
+        path_after(REPLAY_ARG)
+            .map(|path| ReplayMode::Replay {
+                path,
+                headless: args.iter().any(|arg| arg == HEADLESS_ARG),
+            })
+            .or_else(|| path_after(RECORD_ARG).map(ReplayMode::Record))
+    }
+
+    pub fn is_headless(&self) -> bool {
+        matches!(self, ReplayMode::Replay { headless: true, .. })
+    }
+}
+
+/// Player input of every gameplay tick of a run. Ticks are recorded rather than frames since
+/// gameplay only sees input once per tick, whatever the frame rate was.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct InputRecording {
+    /// Input of every player on each tick, in player index order
+    pub ticks: Vec<Vec<PlayerInput>>,
+    /// First player translation after the last tick
+    pub final_translation: Option<Vec2>,
+}
+
+impl InputRecording {
+    /// One line per tick holding the pressed flags in hex and the cursor of each player, or `-`
+    /// when the cursor was outside the window. Players are separated by `|`.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut text = String::new();
+        self.ticks.iter().for_each(|inputs| {
+            let players: Vec<String> = inputs
+                .iter()
+                .map(|input| match input.cursor {
+                    Some(cursor) => {
+                        format!("{:03x} {} {}", input_flags(input), cursor.x, cursor.y)
+                    }
+                    None => format!("{:03x} -", input_flags(input)),
+                })
+                .collect();
+            text.push_str(&players.join(" | "));
+            text.push('\n');
+        });
+        if let Some(translation) = self.final_translation {
+            text.push_str(&format!("end {} {}\n", translation.x, translation.y));
+        }
+        fs::write(path, text)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let mut recording = InputRecording::default();
+        for (index, line) in text.lines().enumerate() {
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}:{}: {:?}", path.display(), index + 1, line),
+                )
+            };
+            let mut words = line.split_whitespace();
+            let float = |words: &mut std::str::SplitWhitespace| -> io::Result<f32> {
+                words
+                    .next()
+                    .and_then(|word| word.parse().ok())
+                    .ok_or_else(invalid)
+            };
+            match words.next() {
+                Some("end") => {
+                    recording.final_translation =
+                        Some(Vec2::new(float(&mut words)?, float(&mut words)?));
+                }
+                Some(_) => {
+                    let inputs = line
+                        .split('|')
+                        .map(|player| {
+                            let mut words = player.split_whitespace();
+                            let flags = words
+                                .next()
+                                .and_then(|flags| u16::from_str_radix(flags, 16).ok())
+                                .ok_or_else(invalid)?;
+                            let cursor = match words.clone().next() {
+                                Some("-") => None,
+                                _ => Some(Vec2::new(float(&mut words)?, float(&mut words)?)),
+                            };
+                            Ok(input_from_flags(flags, cursor))
+                        })
+                        .collect::<io::Result<Vec<_>>>()?;
+                    recording.ticks.push(inputs);
+                }
+                None => (),
+            }
+        }
+        Ok(recording)
+    }
+}
+
+fn input_flags(input: &PlayerInput) -> u16 {
+    [
+        input.left,
+        input.right,
+        input.up,
+        input.down,
+        input.jump,
+        input.crawl,
+        input.reel_in,
+        input.reel_out,
+        input.jump_pressed,
+        input.bridge_pressed,
+        input.shoot_pressed,
+        input.release_pressed,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |flags, (bit, pressed)| {
+        flags | ((*pressed as u16) << bit)
+    })
+}
+
+fn input_from_flags(flags: u16, cursor: Option<Vec2>) -> PlayerInput {
+    let flag = |bit: u16| flags & (1 << bit) != 0;
+    PlayerInput {
+        left: flag(0),
+        right: flag(1),
+        up: flag(2),
+        down: flag(3),
+        jump: flag(4),
+        crawl: flag(5),
+        reel_in: flag(6),
+        reel_out: flag(7),
+        jump_pressed: flag(8),
+        bridge_pressed: flag(9),
+        shoot_pressed: flag(10),
+        release_pressed: flag(11),
+        cursor,
+    }
+}
+
+/// Where the recorded or replayed run is at. Levels take a few frames to spawn, so the run only
+/// starts once the first level is in place, from the same spot every time.
+#[derive(Resource, Debug, Default)]
+pub struct ReplayRun {
+    level_ready: bool,
+    started: bool,
+    stopped: bool,
+    /// Ticks played since the run started
+    tick: usize,
+}
+impl ReplayRun {
+    pub fn is_running(&self) -> bool {
+        self.started && !self.stopped
+    }
+}
+
+const TICK_START: FixedUpdateStage = FixedUpdateStage::RestoreTransforms;
+const TICK_END: FixedUpdateStage = FixedUpdateStage::ResolveComplexForces;
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ReplaySystem {
+    StartRun,
+    RecordFinalTranslation,
+    FinishReplay,
+}
+
+pub struct ReplayPlugin {
+    mode: ReplayMode,
+    run_in: Option<GameState>,
+}
+impl ReplayPlugin {
+    pub fn new(mode: ReplayMode, run_in: Option<GameState>) -> Self {
+        Self { mode, run_in }
+    }
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ReplayRun::default())
+            .add_system_run_if(self.run_in, wait_for_start_level);
+
+        // The run starts before the first position is checked
+        let tick_end = app
+            .add_fixed_chain_run_if(self.run_in, TICK_END)
+            .then(ReplaySystem::StartRun, start_run);
+        match &self.mode {
+            ReplayMode::Record(path) => {
+                tick_end.then(
+                    ReplaySystem::RecordFinalTranslation,
+                    record::record_final_translation,
+                );
+                app.insert_resource(InputRecording::default())
+                    .insert_resource(record::RecordingPath(path.clone()))
+                    .add_fixed_system_run_if(self.run_in, TICK_START, record::record_player_input)
+                    .add_system_to_stage(CoreStage::Last, record::save_recording);
+                if let Some(state) = self.run_in {
+                    // Leaving to the menu throws the level away, a replay couldn't follow
+                    app.add_exit_system(state, record::stop_recording);
+                }
+            }
+            ReplayMode::Replay { path, .. } => {
+                tick_end.then(ReplaySystem::FinishReplay, playback::finish_replay);
+                let recording = InputRecording::load(path)
+                    .unwrap_or_else(|err| panic!("Failed to load replay: {}", err));
+                app.insert_resource(recording).add_fixed_system_run_if(
+                    self.run_in,
+                    TICK_START,
+                    playback::play_back_input,
+                );
+            }
+        }
+    }
+}
+
+fn wait_for_start_level(mut run: ResMut<ReplayRun>, mut evr_level: EventReader<LevelEvent>) {
+    evr_level
+        .iter()
+        .any(|ev| matches!(ev, LevelEvent::Transformed(_)))
+        .then(|| run.level_ready = true);
+}
+
+/// Put the players at the start of the level, at rest, wherever they fell while the level spawned
+fn start_run(
+    mut run: ResMut<ReplayRun>,
+    level_selection: Res<LevelSelection>,
+    mut q_player: Query<(&mut Transform, &mut Velocity), With<Player>>,
+) {
+    if run.started || !run.level_ready || q_player.is_empty() {
+        return;
+    }
+    let start = translation_in_level(tilemap::current_level_index(&level_selection).unwrap_or(0));
+    q_player.for_each_mut(|(mut transform, mut velocity)| {
+        transform.translation = start;
+        *velocity = Velocity::zero();
+    });
+    run.started = true;
+    info!("Replay run started");
+}
@@ -0,0 +1,60 @@
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{data::player::Player, plugins::player::PlayerInput};
+
+use super::{InputRecording, ReplayRun, FINAL_POSITION_TOLERANCE};
+
+/// Overwrite whatever the keyboard and mouse did with the recorded input for this tick
+pub fn play_back_input(
+    mut run: ResMut<ReplayRun>,
+    recording: Res<InputRecording>,
+    mut q_player: Query<(&Player, &mut PlayerInput)>,
+) {
+    let running = run.is_running();
+    let inputs = recording.ticks.get(run.tick).filter(|_| running);
+    q_player.for_each_mut(|(player, mut input)| {
+        *input = inputs
+            .and_then(|inputs| inputs.get(player.index))
+            .cloned()
+            .unwrap_or_default();
+    });
+    running.then(|| run.tick += 1);
+}
+
+/// Once every recorded tick has played, check the player ended up where the recording did.
+/// Panics on a mismatch so headless replays fail the CI job.
+pub fn finish_replay(
+    mut run: ResMut<ReplayRun>,
+    recording: Res<InputRecording>,
+    q_player: Query<(&Player, &Transform)>,
+    mut evw_exit: EventWriter<AppExit>,
+) {
+    if !run.is_running() || run.tick < recording.ticks.len() {
+        return;
+    }
+    let Some((_, transform)) = q_player.iter().find(|(player, _)| player.index == 0) else {
+        return;
+    };
+    run.stopped = true;
+
+    let translation = transform.translation.truncate();
+    match recording.final_translation {
+        Some(expected) => {
+            assert!(
+                translation.distance(expected) <= FINAL_POSITION_TOLERANCE,
+                "Replay ended at {} but the recording ended at {}",
+                translation,
+                expected
+            );
+            info!(
+                "Replay matched the recording, player ended at {}",
+                translation
+            );
+        }
+        None => warn!(
+            "The recording has no final position, player ended at {}",
+            translation
+        ),
+    }
+    evw_exit.send(AppExit);
+}
@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{data::player::Player, plugins::player::PlayerInput};
+
+use super::{InputRecording, ReplayRun};
+
+#[derive(Resource, Debug)]
+pub struct RecordingPath(pub PathBuf);
+
+pub fn record_player_input(
+    run: Res<ReplayRun>,
+    mut q_player: Query<(&Player, &mut PlayerInput)>,
+    mut recording: ResMut<InputRecording>,
+) {
+    if !run.started {
+        // Whatever was pressed while the level spawned isn't part of the run
+        q_player.for_each_mut(|(_, mut input)| *input = PlayerInput::default());
+        return;
+    }
+    if run.is_running() {
+        let mut inputs: Vec<_> = q_player.iter().collect();
+        inputs.sort_by_key(|(player, _)| player.index);
+        recording
+            .ticks
+            .push(inputs.into_iter().map(|(_, input)| input.clone()).collect());
+    }
+}
+
+pub fn record_final_translation(
+    run: Res<ReplayRun>,
+    q_player: Query<(&Player, &Transform)>,
+    mut recording: ResMut<InputRecording>,
+) {
+    if !run.is_running() {
+        return;
+    }
+    if let Some((_, transform)) = q_player.iter().find(|(player, _)| player.index == 0) {
+        recording.final_translation = Some(transform.translation.truncate());
+    }
+}
+
+pub fn stop_recording(mut run: ResMut<ReplayRun>) {
+    run.stopped = true;
+}
+
+pub fn save_recording(
+    path: Res<RecordingPath>,
+    recording: Res<InputRecording>,
+    evr_exit: EventReader<AppExit>,
+) {
+    if evr_exit.is_empty() {
+        return;
+    }
+    match recording.save(&path.0) {
+        Ok(_) => info!(
+            "Saved {} ticks of input to {}",
+            recording.ticks.len(),
+            path.0.display()
+        ),
+        Err(err) => error!("Failed to save recording to {}: {}", path.0.display(), err),
+    }
+}
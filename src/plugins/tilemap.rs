@@ -1,58 +1,396 @@
 use std::time::Duration;
 
-use crate::{data::tilemap::*, utils::state_helper::StateExtend, GameState};
+use crate::{
+    data::{
+        enemy::EnemyBundle,
+        physics::GameCollisionGroups,
+        platform::{
+            FallingHazardBundle, MetalPlatformBundle, MovingHazardBundle, MovingPlatformBundle,
+        },
+        tilemap::*,
+    },
+    plugins::physics::{FixedEventExt, FixedUpdateStage},
+    utils::state_helper::StateExtend,
+    GameState,
+};
 use bevy::prelude::*;
-use bevy_ecs_ldtk::prelude::*;
+use bevy_ecs_ldtk::{prelude::*, LdtkStage, LdtkSystemLabel};
 
+mod breakable;
+mod coop;
 mod modify;
+mod platform;
 mod spawn;
+mod streaming;
+
+/// Labels of the tilemap systems running every tick, in the order they run
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum TilemapSystem {
+    UpdateLevelSelection,
+    CollectCoin,
+    SpawnCredits,
+    SpawnSurfaceEdges,
+    PressCoopSwitches,
+    OpenCoopDoors,
+    MoveBodies,
+    CarryGroundedPlayers,
+    DropFallingHazards,
+    CrumbleTiles,
+    BreakTiles,
+}
 
 const MAP_PATH: &str = "ldtk.ldtk";
 const START_LEVEL: usize = 0;
-const LEVELS: &[&str] = &[
+pub const LEVELS: &[&str] = &[
     "Level_0", "Level_1", "Level_2", "Level_3", "Level_4", "Level_5",
 ];
 // layers
 const DARK_CAVE_LAYER: &str = "DarkCave";
 const SPIKES_LAYER: &str = "Spikes";
 const SURFACE_LAYER: &str = "Surface";
-// dark cave
-const DARK_TERRAIN: i32 = 1;
-const PINK_TERRAIN: i32 = 2;
-const METAL: i32 = 3;
-// spikes
-const SPIKE_UP: i32 = 1;
-const SPIKE_DOWN: i32 = 2;
-const SPIKE_LEFT: i32 = 3;
-const SPIKE_RIGHT: i32 = 4;
-// surface
-const GRASS: i32 = 1;
-const DIRT: i32 = 3;
-const EDGE: i32 = 4;
-const CREDITS_SENSOR: i32 = 5;
-const EDGE_SENSOR: i32 = 6;
+const CONVEYOR_SPEED: f32 = 40.0;
+/// Damage of the sharp spikes, values 5 to 8 of the spikes layer
+pub const SHARP_SPIKE_DAMAGE: i32 = 2;
+const TERRAIN: IntGridTile = IntGridTile {
+    layer: DARK_CAVE_LAYER,
+    value: 1,
+    kind: TileKind::Terrain,
+    colliders: TileColliders::Merged {
+        groups: GameCollisionGroups::NON_STICK_TERRAIN,
+        material: SurfaceMaterial::DEFAULT,
+    },
+    insert: |tile| {
+        tile.insert(TerrainBundle::default());
+    },
+};
+const SPIKE: IntGridTile = IntGridTile {
+    layer: SPIKES_LAYER,
+    value: 1,
+    kind: TileKind::SpikeUp,
+    colliders: TileColliders::Spike {
+        direction: SpikeDirection::Up,
+        damage: Trap::DEFAULT_DAMAGE,
+    },
+    // The trap comes with the spike colliders, pointing their way
+    insert: |_| {},
+};
+/// Every int-grid value spawned as a tile, by layer and value
+pub const INT_GRID_TILES: &[IntGridTile] = &[
+    // dark terrain
+    TERRAIN,
+    // pink terrain
+    IntGridTile {
+        value: 2,
+        ..TERRAIN
+    },
+    IntGridTile {
+        value: 3,
+        kind: TileKind::Metal,
+        colliders: TileColliders::Merged {
+            groups: GameCollisionGroups::WEB_STICKABLE_TERRAIN,
+            material: SurfaceMaterial::DEFAULT,
+        },
+        insert: |tile| {
+            tile.insert(WebStickerBundle::default());
+        },
+        ..TERRAIN
+    },
+    IntGridTile {
+        value: 5,
+        kind: TileKind::Crumbling,
+        colliders: TileColliders::PerTile {
+            groups: GameCollisionGroups::NON_STICK_TERRAIN,
+            material: SurfaceMaterial::DEFAULT,
+            color: Color::rgb(0.55, 0.4, 0.3),
+        },
+        insert: |tile| {
+            tile.insert(CrumblingBundle::default());
+        },
+        ..TERRAIN
+    },
+    IntGridTile {
+        value: 6,
+        kind: TileKind::Breakable,
+        colliders: TileColliders::PerTile {
+            groups: GameCollisionGroups::NON_STICK_TERRAIN,
+            material: SurfaceMaterial::DEFAULT,
+            color: Color::rgb(0.5, 0.5, 0.6),
+        },
+        insert: |tile| {
+            tile.insert(BreakableBundle::default());
+        },
+        ..TERRAIN
+    },
+    IntGridTile {
+        value: 7,
+        kind: TileKind::Ice,
+        colliders: TileColliders::Merged {
+            groups: GameCollisionGroups::NON_STICK_TERRAIN,
+            material: SurfaceMaterial::ICE,
+        },
+        ..TERRAIN
+    },
+    IntGridTile {
+        value: 8,
+        kind: TileKind::Bouncy,
+        colliders: TileColliders::Merged {
+            groups: GameCollisionGroups::NON_STICK_TERRAIN,
+            material: SurfaceMaterial::BOUNCY,
+        },
+        ..TERRAIN
+    },
+    IntGridTile {
+        value: 9,
+        kind: TileKind::Sticky,
+        colliders: TileColliders::Merged {
+            groups: GameCollisionGroups::NON_STICK_TERRAIN,
+            material: SurfaceMaterial::STICKY,
+        },
+        ..TERRAIN
+    },
+    IntGridTile {
+        value: 10,
+        kind: TileKind::ConveyorLeft,
+        colliders: TileColliders::Merged {
+            groups: GameCollisionGroups::NON_STICK_TERRAIN,
+            material: SurfaceMaterial::conveyor(-CONVEYOR_SPEED),
+        },
+        ..TERRAIN
+    },
+    IntGridTile {
+        value: 11,
+        kind: TileKind::ConveyorRight,
+        colliders: TileColliders::Merged {
+            groups: GameCollisionGroups::NON_STICK_TERRAIN,
+            material: SurfaceMaterial::conveyor(CONVEYOR_SPEED),
+        },
+        ..TERRAIN
+    },
+    // spikes
+    SPIKE,
+    IntGridTile {
+        value: 2,
+        kind: TileKind::SpikeDown,
+        colliders: TileColliders::Spike {
+            direction: SpikeDirection::Down,
+            damage: Trap::DEFAULT_DAMAGE,
+        },
+        ..SPIKE
+    },
+    IntGridTile {
+        value: 3,
+        kind: TileKind::SpikeLeft,
+        colliders: TileColliders::Spike {
+            direction: SpikeDirection::Left,
+            damage: Trap::DEFAULT_DAMAGE,
+        },
+        ..SPIKE
+    },
+    IntGridTile {
+        value: 4,
+        kind: TileKind::SpikeRight,
+        colliders: TileColliders::Spike {
+            direction: SpikeDirection::Right,
+            damage: Trap::DEFAULT_DAMAGE,
+        },
+        ..SPIKE
+    },
+    // sharp spikes
+    IntGridTile {
+        value: 5,
+        kind: TileKind::SharpSpikeUp,
+        colliders: TileColliders::Spike {
+            direction: SpikeDirection::Up,
+            damage: SHARP_SPIKE_DAMAGE,
+        },
+        ..SPIKE
+    },
+    IntGridTile {
+        value: 6,
+        kind: TileKind::SharpSpikeDown,
+        colliders: TileColliders::Spike {
+            direction: SpikeDirection::Down,
+            damage: SHARP_SPIKE_DAMAGE,
+        },
+        ..SPIKE
+    },
+    IntGridTile {
+        value: 7,
+        kind: TileKind::SharpSpikeLeft,
+        colliders: TileColliders::Spike {
+            direction: SpikeDirection::Left,
+            damage: SHARP_SPIKE_DAMAGE,
+        },
+        ..SPIKE
+    },
+    IntGridTile {
+        value: 8,
+        kind: TileKind::SharpSpikeRight,
+        colliders: TileColliders::Spike {
+            direction: SpikeDirection::Right,
+            damage: SHARP_SPIKE_DAMAGE,
+        },
+        ..SPIKE
+    },
+    // grass
+    IntGridTile {
+        layer: SURFACE_LAYER,
+        ..TERRAIN
+    },
+    // dirt
+    IntGridTile {
+        layer: SURFACE_LAYER,
+        value: 3,
+        ..TERRAIN
+    },
+    IntGridTile {
+        layer: SURFACE_LAYER,
+        value: 4,
+        kind: TileKind::Edge,
+        colliders: TileColliders::None,
+        insert: |tile| {
+            tile.insert(Edge);
+        },
+    },
+    IntGridTile {
+        layer: SURFACE_LAYER,
+        value: 5,
+        kind: TileKind::CreditsSensor,
+        colliders: TileColliders::Sensor,
+        insert: |tile| {
+            tile.insert(CreditsSensor);
+        },
+    },
+    IntGridTile {
+        layer: SURFACE_LAYER,
+        value: 6,
+        kind: TileKind::EdgeSensor,
+        colliders: TileColliders::Sensor,
+        insert: |tile| {
+            tile.insert(EdgeSensor);
+        },
+    },
+];
+
 const CREDITS: &str = "Credits";
 pub const COIN: &str = "Coin";
+pub const HAZARD: &str = "Hazard";
+pub const CRATE: &str = "Crate";
+pub const LEVER: &str = "Lever";
+pub const COOP_SWITCH: &str = "CoopSwitch";
+pub const COOP_DOOR: &str = "CoopDoor";
+pub const ENEMY: &str = "Enemy";
+pub const MOVING_PLATFORM: &str = "MovingPlatform";
+pub const METAL_PLATFORM: &str = "MetalPlatform";
+pub const MOVING_HAZARD: &str = "MovingHazard";
+pub const FALLING_HAZARD: &str = "FallingHazard";
+pub const WEB_UPGRADE: &str = "WebUpgrade";
+// entity fields
+pub const DAMAGE_FIELD: &str = "Damage";
+pub const UPGRADE_FIELD: &str = "Upgrade";
+pub const CHANNEL_FIELD: &str = "Channel";
+pub const KIND_FIELD: &str = "Kind";
+pub const SIGHT_FIELD: &str = "Sight";
+pub const PATROL_FIELD: &str = "Patrol";
+pub const SPEED_FIELD: &str = "Speed";
+// level fields
+/// Bool field of a level, its spikes get a few merged colliders instead of one per tile
+pub const MERGE_SPIKES_FIELD: &str = "MergeSpikes";
 
 pub const TILE_HALF_SIZE: (f32, f32) = (4.0, 4.0);
 const TEXT_FONT_PATH: &str = "ThaleahFat.ttf";
-const CREDITS_LEVEL_INDEX: usize = LEVELS.len() - 1;
+pub const CREDITS_LEVEL_INDEX: usize = LEVELS.len() - 1;
+
+/// Sent when a [`CoopDoor`] opens or closes
+#[derive(Debug)]
+pub enum CoopDoorEvent {
+    Opened(Entity),
+    Closed(Entity),
+}
+
+/// Sent when a [`Crumbling`] tile falls away or comes back, or a [`Breakable`] tile breaks
+#[derive(Debug)]
+pub enum TileEvent {
+    Crumbled(Entity),
+    Restored(Entity),
+    Broke(Entity),
+}
+
+/// Sent as a [`crate::data::platform::FallingHazard`] goes through its fall
+#[derive(Debug)]
+pub enum FallingHazardEvent {
+    Dropped(Entity),
+    Landed(Entity),
+    Reset(Entity),
+}
 
 #[derive(Resource, Default)]
-pub struct FontHandle(Handle<Font>);
+pub struct FontHandle(pub Handle<Font>);
 
 pub struct LevelChanged {
-    previous: usize,
-    current: usize,
+    pub previous: usize,
+    pub current: usize,
+}
+
+/// LDtk file the levels are spawned from, relative to the assets folder
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct MapPath(pub &'static str);
+
+/// Which levels stay spawned around the selected one, the others are despawned with everything
+/// in them
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct LevelStreaming {
+    /// Levels at most this many pixels away from the selected one are spawned, 0 only keeps the
+    /// ones touching it
+    pub radius: f32,
+    /// Most levels spawned at once, the closest ones are kept. Leaving out a neighbour keeps the
+    /// player from walking into it.
+    pub max_levels: usize,
+}
+
+impl Default for LevelStreaming {
+    fn default() -> Self {
+        Self {
+            radius: 0.0,
+            max_levels: 9,
+        }
+    }
+}
+
+/// What the spawned levels hold, to keep an eye on the cost of bigger worlds
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LevelStats {
+    pub levels: usize,
+    /// Entities in the levels, tiles and colliders included
+    pub entities: usize,
+    pub colliders: usize,
 }
 
 pub struct TilemapPlugin {
     run_in: Option<GameState>,
+    map_path: &'static str,
+    streaming: LevelStreaming,
 }
 
 impl TilemapPlugin {
     pub fn new(state: Option<GameState>) -> Self {
-        Self { run_in: state }
+        Self {
+            run_in: state,
+            map_path: MAP_PATH,
+            streaming: LevelStreaming::default(),
+        }
+    }
+
+    /// Spawn the levels from another LDtk file, its levels must be named like [`LEVELS`]
+    pub fn with_map_path(mut self, map_path: &'static str) -> Self {
+        self.map_path = map_path;
+        self
+    }
+
+    /// Keep more or fewer levels spawned around the selected one
+    pub fn with_level_streaming(mut self, streaming: LevelStreaming) -> Self {
+        self.streaming = streaming;
+        self
     }
 }
 
@@ -61,47 +399,86 @@ impl Plugin for TilemapPlugin {
         app.add_plugin(LdtkPlugin)
             .insert_resource(LdtkSettings {
                 set_clear_color: SetClearColor::No,
+                // Levels around the selected one are picked by streaming::stream_levels
                 level_spawn_behavior: LevelSpawnBehavior::UseWorldTranslation {
-                    load_level_neighbors: true,
+                    load_level_neighbors: false,
                 },
                 ..default()
             })
             .insert_resource(LevelSelection::Identifier(String::from(
                 LEVELS[START_LEVEL],
             )))
+            .insert_resource(MapPath(self.map_path))
+            .insert_resource(self.streaming)
+            .init_resource::<LevelStats>()
             .insert_resource(FontHandle::default())
             .insert_resource(modify::CreditTimer {
                 timer: Timer::new(Duration::from_secs_f32(1.25), TimerMode::Repeating),
                 line_count: 0,
                 active: false,
             })
-            .add_event::<LevelChanged>()
-            // ldtk incels
-            .register_ldtk_int_cell_for_layer::<TerrainTileBundle>(DARK_CAVE_LAYER, DARK_TERRAIN)
-            .register_ldtk_int_cell_for_layer::<TerrainTileBundle>(DARK_CAVE_LAYER, PINK_TERRAIN)
-            .register_ldtk_int_cell_for_layer::<WebStickerTileBundle>(DARK_CAVE_LAYER, METAL)
-            .register_ldtk_int_cell_for_layer::<TrapTileBundle>(SPIKES_LAYER, SPIKE_UP)
-            .register_ldtk_int_cell_for_layer::<TrapTileBundle>(SPIKES_LAYER, SPIKE_DOWN)
-            .register_ldtk_int_cell_for_layer::<TrapTileBundle>(SPIKES_LAYER, SPIKE_LEFT)
-            .register_ldtk_int_cell_for_layer::<TrapTileBundle>(SPIKES_LAYER, SPIKE_RIGHT)
-            .register_ldtk_int_cell_for_layer::<TerrainTileBundle>(SURFACE_LAYER, GRASS)
-            .register_ldtk_int_cell_for_layer::<TerrainTileBundle>(SURFACE_LAYER, DIRT)
-            .register_ldtk_int_cell_for_layer::<EdgeBundle>(SURFACE_LAYER, EDGE)
-            .register_ldtk_int_cell_for_layer::<CreditsSensorBundle>(SURFACE_LAYER, CREDITS_SENSOR)
-            .register_ldtk_int_cell_for_layer::<EdgeSensorBundle>(SURFACE_LAYER, EDGE_SENSOR)
+            .add_fixed_event::<LevelChanged>()
+            .add_fixed_event::<CoopDoorEvent>()
+            .add_fixed_event::<FallingHazardEvent>()
+            .add_fixed_event::<TileEvent>()
             // ldtk entities
             .register_ldtk_entity::<CoinBundle>(COIN)
             .register_ldtk_entity::<CreditsBundle>(CREDITS)
+            .register_ldtk_entity::<HazardBundle>(HAZARD)
+            .register_ldtk_entity::<CrateBundle>(CRATE)
+            .register_ldtk_entity::<LeverBundle>(LEVER)
+            .register_ldtk_entity::<CoopSwitchBundle>(COOP_SWITCH)
+            .register_ldtk_entity::<CoopDoorBundle>(COOP_DOOR)
+            .register_ldtk_entity::<EnemyBundle>(ENEMY)
+            .register_ldtk_entity::<MovingPlatformBundle>(MOVING_PLATFORM)
+            .register_ldtk_entity::<MetalPlatformBundle>(METAL_PLATFORM)
+            .register_ldtk_entity::<MovingHazardBundle>(MOVING_HAZARD)
+            .register_ldtk_entity::<FallingHazardBundle>(FALLING_HAZARD)
+            .register_ldtk_entity::<WebUpgradeBundle>(WEB_UPGRADE)
             // systems
             .add_startup_system_if_state(self.run_in, setup)
             .add_startup_system_if_state(self.run_in, spawn::spawn_tilemap)
+            .add_system_to_stage(
+                LdtkStage::ProcessApi,
+                streaming::stream_levels
+                    .after(LdtkSystemLabel::LevelSelection)
+                    .before(LdtkSystemLabel::LevelSet),
+            )
             .add_system_run_if(self.run_in, spawn::spawn_tile_colliders)
-            .add_system_run_if(self.run_in, modify::update_level_selection)
+            .add_system_run_if(self.run_in, streaming::count_level_entities)
             .add_system_run_if(self.run_in, modify::camera_fit_inside_current_level)
-            .add_system_run_if(self.run_in, modify::collect_coin)
-            .add_system_run_if(self.run_in, modify::spawn_credits)
-            .add_system_run_if(self.run_in, modify::spawn_surface_edges)
             .add_system_run_if(self.run_in, modify::update_credit_timer);
+        app.add_fixed_chain_run_if(self.run_in, FixedUpdateStage::World)
+            // respawns depend on the level selection, keep it in step with the player
+            .then(
+                TilemapSystem::UpdateLevelSelection,
+                modify::update_level_selection,
+            )
+            // player collisions are sent every tick
+            .then(TilemapSystem::CollectCoin, modify::collect_coin)
+            .then(TilemapSystem::SpawnCredits, modify::spawn_credits)
+            .then(
+                TilemapSystem::SpawnSurfaceEdges,
+                modify::spawn_surface_edges,
+            )
+            .then(TilemapSystem::PressCoopSwitches, coop::press_coop_switches)
+            .then(TilemapSystem::OpenCoopDoors, coop::open_coop_doors)
+            .then(TilemapSystem::MoveBodies, platform::move_bodies)
+            .then(
+                TilemapSystem::CarryGroundedPlayers,
+                platform::carry_grounded_players,
+            )
+            .then(
+                TilemapSystem::DropFallingHazards,
+                platform::drop_falling_hazards,
+            )
+            .then(TilemapSystem::CrumbleTiles, breakable::crumble_tiles)
+            .then(TilemapSystem::BreakTiles, breakable::break_tiles);
+        // ldtk incels
+        check_int_grid_tiles();
+        INT_GRID_TILES.iter().for_each(|tile| {
+            app.register_ldtk_int_cell_for_layer::<IntGridTileBundle>(tile.layer, tile.value);
+        });
     }
 }
 
@@ -109,6 +486,28 @@ fn setup(asset_server: Res<AssetServer>, mut font_handle: ResMut<FontHandle>) {
     font_handle.0 = asset_server.load(TEXT_FONT_PATH);
 }
 
+/// Every layer value has a single row, and rows sharing a kind spawn the same colliders and
+/// components since their tiles are merged
+fn check_int_grid_tiles() {
+    INT_GRID_TILES.iter().enumerate().for_each(|(index, tile)| {
+        INT_GRID_TILES[..index].iter().for_each(|other| {
+            assert!(
+                (other.layer, other.value) != (tile.layer, tile.value),
+                "value {} of the {} layer has several rows",
+                tile.value,
+                tile.layer
+            );
+            assert!(
+                other.kind != tile.kind
+                    || (other.colliders == tile.colliders
+                        && other.insert as usize == tile.insert as usize),
+                "rows of {:?} tiles spawn different colliders or components",
+                tile.kind
+            );
+        });
+    });
+}
+
 pub fn current_level_index(level_selection: &LevelSelection) -> Option<usize> {
     if let LevelSelection::Identifier(ref id) = *level_selection {
         LEVELS.iter().position(|level| *level == id)
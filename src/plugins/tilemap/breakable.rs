@@ -0,0 +1,135 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    data::{
+        physics::{CollisionGroupsFilter, GameCollisionGroups},
+        player::{Player, PlayerFoot},
+        tilemap::{Breakable, Crumbling, CrumblingState},
+        web::WebHead,
+    },
+    plugins::{physics::FIXED_TIMESTEP, player::lifecycle::player_of_collider},
+};
+
+use super::TileEvent;
+
+const CRUMBLING_TILE_ALPHA: f32 = 0.6;
+
+/// Start crumbling tiles a player stands on, let them fall away once their time is up and bring
+/// them back later
+pub fn crumble_tiles(
+    mut q_tile: Query<(Entity, &mut Crumbling, &mut CollisionGroups, &mut Sprite)>,
+    q_foot: Query<(), With<PlayerFoot>>,
+    rapier_context: Res<RapierContext>,
+    mut evw_tile: EventWriter<TileEvent>,
+) {
+    q_tile.for_each_mut(|(entity, mut crumbling, mut groups, mut sprite)| {
+        let next_state = match crumbling.state {
+            CrumblingState::Solid => {
+                let stood_on = rapier_context
+                    .intersections_with(entity)
+                    .filter(|(_, _, intersecting)| *intersecting)
+                    .any(|(entity1, entity2, _)| {
+                        q_foot.contains(entity1) || q_foot.contains(entity2)
+                    });
+                if !stood_on {
+                    return;
+                }
+                sprite.color.set_a(CRUMBLING_TILE_ALPHA);
+                CrumblingState::Crumbling {
+                    remaining: crumbling.crumble_delay,
+                }
+            }
+            CrumblingState::Crumbling { remaining } if remaining > FIXED_TIMESTEP => {
+                CrumblingState::Crumbling {
+                    remaining: remaining - FIXED_TIMESTEP,
+                }
+            }
+            CrumblingState::Crumbling { .. } => {
+                groups.filters = Group::NONE;
+                sprite.color.set_a(0.0);
+                evw_tile.send(TileEvent::Crumbled(entity));
+                CrumblingState::Gone {
+                    remaining: crumbling.respawn_delay,
+                }
+            }
+            CrumblingState::Gone { remaining } if remaining > FIXED_TIMESTEP => {
+                CrumblingState::Gone {
+                    remaining: remaining - FIXED_TIMESTEP,
+                }
+            }
+            CrumblingState::Gone { .. } => {
+                groups.filters = GameCollisionGroups::NON_STICK_TERRAIN.filter_group();
+                sprite.color.set_a(1.0);
+                evw_tile.send(TileEvent::Restored(entity));
+                CrumblingState::Solid
+            }
+        };
+        crumbling.state = next_state;
+    });
+}
+
+/// Break tiles hit by a web or by a player going fast enough. The player smashes through and
+/// keeps going with the speed it had before the hit.
+pub fn break_tiles(
+    q_breakable: Query<&Breakable>,
+    q_web_head: Query<(), With<WebHead>>,
+    mut q_player: Query<(Entity, &mut Velocity), With<Player>>,
+    q_parents: Query<&Parent>,
+    mut evr_collisions: EventReader<CollisionEvent>,
+    mut evw_tile: EventWriter<TileEvent>,
+    mut commands: Commands,
+    // Collisions are only seen after the solver stopped the player, this is the speed of the
+    // tick before
+    mut previous_velocities: Local<HashMap<Entity, Vec2>>,
+) {
+    let mut broken = HashSet::new();
+    for collision in evr_collisions.iter() {
+        let CollisionEvent::Started(entity_one, entity_two, _) = collision else {
+            continue;
+        };
+        let Some((tile, breakable, other)) = q_breakable
+            .get(*entity_one)
+            .map(|breakable| (*entity_one, breakable, *entity_two))
+            .or_else(|_| {
+                q_breakable
+                    .get(*entity_two)
+                    .map(|breakable| (*entity_two, breakable, *entity_one))
+            })
+            .ok()
+        else {
+            continue;
+        };
+        if broken.contains(&tile) {
+            continue;
+        }
+
+        let is_player = |entity| q_player.contains(entity);
+        let crashed_player = player_of_collider(&q_parents, is_player, other).filter(|player| {
+            previous_velocities
+                .get(player)
+                .map_or(false, |velocity| velocity.length() >= breakable.break_speed)
+        });
+        if !q_web_head.contains(other) && crashed_player.is_none() {
+            continue;
+        }
+
+        if let Some(player) = crashed_player {
+            if let (Ok((_, mut velocity)), Some(previous)) =
+                (q_player.get_mut(player), previous_velocities.get(&player))
+            {
+                velocity.linvel = *previous;
+            }
+        }
+        broken.insert(tile);
+        commands.entity(tile).despawn_recursive();
+        evw_tile.send(TileEvent::Broke(tile));
+    }
+
+    previous_velocities.clear();
+    q_player.for_each(|(player, velocity)| {
+        previous_velocities.insert(player, velocity.linvel);
+    });
+}
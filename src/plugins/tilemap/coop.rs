@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    data::{
+        physics::{CollisionGroupsFilter, GameCollisionGroups},
+        player::Player,
+        tilemap::{CoopDoor, CoopSwitch},
+    },
+    plugins::player::{lifecycle::player_of_collider, PlayerCount},
+};
+
+use super::CoopDoorEvent;
+
+const OPEN_DOOR_ALPHA: f32 = 0.2;
+
+/// Hold down switches with a player standing on them
+pub fn press_coop_switches(
+    mut q_switch: Query<(Entity, &mut CoopSwitch)>,
+    q_player: Query<Entity, With<Player>>,
+    q_parents: Query<&Parent>,
+    rapier_context: Res<RapierContext>,
+) {
+    q_switch.for_each_mut(|(entity, mut switch)| {
+        let pressed_by = rapier_context
+            .intersections_with(entity)
+            .filter(|(_, _, intersecting)| *intersecting)
+            .map(|(entity1, entity2, _)| (entity1 == entity).then_some(entity2).unwrap_or(entity1))
+            .find_map(|other| {
+                player_of_collider(&q_parents, |player| q_player.contains(player), other)
+            });
+        switch.pressed_by = pressed_by;
+    });
+}
+
+/// Open doors while every switch of their channel is held by a different player, so one player
+/// can't solve a co-op puzzle alone
+pub fn open_coop_doors(
+    q_switch: Query<&CoopSwitch>,
+    mut q_door: Query<(
+        Entity,
+        &mut CoopDoor,
+        &mut CollisionGroups,
+        &mut TextureAtlasSprite,
+    )>,
+    player_count: Res<PlayerCount>,
+    mut evw_door: EventWriter<CoopDoorEvent>,
+) {
+    q_door.for_each_mut(|(entity, mut door, mut groups, mut sprite)| {
+        let mut pressed_by: Vec<Option<Entity>> = q_switch
+            .iter()
+            .filter(|switch| switch.channel == door.channel)
+            .map(|switch| switch.pressed_by)
+            .collect();
+        let switch_count = pressed_by.len();
+        pressed_by.sort();
+        pressed_by.dedup();
+        let is_held = switch_count > 0
+            && pressed_by.iter().all(Option::is_some)
+            && pressed_by.len() == switch_count;
+
+        let is_open = player_count.0 < 2 || is_held;
+        if door.is_open == is_open {
+            return;
+        }
+        door.is_open = is_open;
+        groups.filters = is_open
+            .then_some(Group::NONE)
+            .unwrap_or_else(|| GameCollisionGroups::NON_STICK_TERRAIN.filter_group());
+        sprite
+            .color
+            .set_a(is_open.then_some(OPEN_DOOR_ALPHA).unwrap_or(1.0));
+        evw_door.send(
+            is_open
+                .then_some(CoopDoorEvent::Opened(entity))
+                .unwrap_or(CoopDoorEvent::Closed(entity)),
+        );
+    });
+}
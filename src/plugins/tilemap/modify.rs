@@ -1,9 +1,9 @@
 use bevy::prelude::*;
 use bevy::text::Text2dBounds;
 
-use crate::data::player::Player;
+use crate::data::player::{Player, PlayerCamera};
 use crate::data::tilemap::*;
-use crate::plugins::player::PlayerEvent;
+use crate::plugins::{base::CameraMode, player::PlayerEvent};
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
 
@@ -19,58 +19,85 @@ pub struct CreditTimer {
     pub active: bool,
 }
 
+/// Fit the cameras inside the level they look at. The shared camera follows the midpoint of the
+/// players inside the selected level, split cameras follow their own player in whichever level it
+/// is in.
 pub fn camera_fit_inside_current_level(
     mut camera_query: Query<(
+        &PlayerCamera,
         &mut bevy::render::camera::OrthographicProjection,
         &mut Transform,
     )>,
-    player_query: Query<&GlobalTransform, With<Player>>,
+    player_query: Query<(&Player, &GlobalTransform)>,
     level_query: Query<(&Transform, &Handle<LdtkLevel>), Without<OrthographicProjection>>,
     ldtk_levels: Res<Assets<LdtkLevel>>,
     level_selection: Res<LevelSelection>,
+    camera_mode: Res<CameraMode>,
 ) {
-    let (mut orthographic_projection, mut camera_transform) = camera_query.single_mut();
-    let player_translation = player_query.single().translation();
+    // Split cameras sit side by side, each one gets a slice of the window width
+    let aspect_ratio = ASPECT_RATIO / camera_query.iter().count().max(1) as f32;
 
-    for (level_transform, level_handle) in &level_query {
-        if let Some(ldtk_level) = ldtk_levels.get(level_handle) {
-            let level = &ldtk_level.level;
-            if level_selection.is_match(&0, level) {
-                let level_ratio = level.px_wid as f32 / ldtk_level.level.px_hei as f32;
+    for (player_camera, mut orthographic_projection, mut camera_transform) in &mut camera_query {
+        let followed: Vec<Vec3> = player_query
+            .iter()
+            .filter(|(player, _)| {
+                *camera_mode == CameraMode::Shared || player.index == player_camera.index
+            })
+            .map(|(_, transform)| transform.translation())
+            .collect();
+        if followed.is_empty() {
+            continue;
+        }
+        let player_translation = followed.iter().sum::<Vec3>() / followed.len() as f32;
 
-                orthographic_projection.scaling_mode = bevy::render::camera::ScalingMode::None;
-                orthographic_projection.bottom = 0.;
-                orthographic_projection.left = 0.;
-                if level_ratio > ASPECT_RATIO {
-                    // level is wider than the screen
-                    orthographic_projection.top = (level.px_hei as f32 / 9.).round() * 9.;
-                    orthographic_projection.right = orthographic_projection.top * ASPECT_RATIO;
-                    camera_transform.translation.x = (player_translation.x
-                        - level_transform.translation.x
-                        - orthographic_projection.right / 2.)
-                        .clamp(0., level.px_wid as f32 - orthographic_projection.right);
-                    camera_transform.translation.y = 0.;
-                } else {
-                    // level is taller than the screen
-                    orthographic_projection.right = (level.px_wid as f32 / 16.).round() * 16.;
-                    orthographic_projection.top = orthographic_projection.right / ASPECT_RATIO;
-                    camera_transform.translation.y = (player_translation.y
-                        - level_transform.translation.y
-                        - orthographic_projection.top / 2.)
-                        .clamp(0., level.px_hei as f32 - orthographic_projection.top);
-                    camera_transform.translation.x = 0.;
-                }
+        for (level_transform, level_handle) in &level_query {
+            let Some(ldtk_level) = ldtk_levels.get(level_handle) else {
+                continue;
+            };
+            let level = &ldtk_level.level;
+            let level_size = Vec2::new(level.px_wid as f32, level.px_hei as f32);
+            let level_min = level_transform.translation.truncate();
+            let is_followed_level = match *camera_mode {
+                CameraMode::Shared => level_selection.is_match(&0, level),
+                CameraMode::Split => Rect::from_corners(level_min, level_min + level_size)
+                    .contains(player_translation.truncate()),
+            };
+            if !is_followed_level {
+                continue;
+            }
+            let level_ratio = level_size.x / level_size.y;
 
-                camera_transform.translation.x += level_transform.translation.x;
-                camera_transform.translation.y += level_transform.translation.y;
+            orthographic_projection.scaling_mode = bevy::render::camera::ScalingMode::None;
+            orthographic_projection.bottom = 0.;
+            orthographic_projection.left = 0.;
+            if level_ratio > aspect_ratio {
+                // level is wider than the screen
+                orthographic_projection.top = (level_size.y / 9.).round() * 9.;
+                orthographic_projection.right = orthographic_projection.top * aspect_ratio;
+                camera_transform.translation.x =
+                    (player_translation.x - level_min.x - orthographic_projection.right / 2.)
+                        .clamp(0., (level_size.x - orthographic_projection.right).max(0.));
+                camera_transform.translation.y = 0.;
+            } else {
+                // level is taller than the screen
+                orthographic_projection.right = (level_size.x / 16.).round() * 16.;
+                orthographic_projection.top = orthographic_projection.right / aspect_ratio;
+                camera_transform.translation.y =
+                    (player_translation.y - level_min.y - orthographic_projection.top / 2.)
+                        .clamp(0., (level_size.y - orthographic_projection.top).max(0.));
+                camera_transform.translation.x = 0.;
             }
+
+            camera_transform.translation.x += level_min.x;
+            camera_transform.translation.y += level_min.y;
         }
     }
 }
 
+/// Select the level the first player is in, the others are free to wander into other levels
 pub fn update_level_selection(
     level_query: Query<(&Handle<LdtkLevel>, &Transform), Without<Player>>,
-    player_query: Query<&Transform, With<Player>>,
+    player_query: Query<(&Player, &Transform)>,
     mut level_selection: ResMut<LevelSelection>,
     ldtk_levels: Res<Assets<LdtkLevel>>,
     mut evw_level_changed: EventWriter<LevelChanged>,
@@ -86,7 +113,8 @@ pub fn update_level_selection(
                 ),
             };
 
-            for player_transform in &player_query {
+            let first_player = player_query.iter().filter(|(player, _)| player.index == 0);
+            for (_, player_transform) in first_player {
                 if player_transform.translation.x < level_bounds.max.x
                     && player_transform.translation.x > level_bounds.min.x
                     && player_transform.translation.y < level_bounds.max.y
@@ -280,7 +308,7 @@ pub fn collect_coin(
 
 fn if_collide_event(ev: &&PlayerEvent) -> Option<Entity> {
     match ev {
-        PlayerEvent::Collided(entity) => Some(*entity),
+        PlayerEvent::Collided(_, entity) => Some(*entity),
         _ => None,
     }
 }
@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    data::{
+        enemy::PatrolPath,
+        platform::{FallingHazard, FallingHazardState, MovingBody},
+        player::{Player, PlayerFoot, PlayerInfo},
+        tilemap::Platform,
+    },
+    plugins::physics::FIXED_TIMESTEP,
+};
+
+use super::{FallingHazardEvent, TILE_HALF_SIZE};
+
+/// Move platforms and swinging hazards toward their next waypoint at a constant speed
+pub fn move_bodies(mut q_body: Query<(&mut MovingBody, &mut PatrolPath, &mut Transform)>) {
+    q_body.for_each_mut(|(mut body, mut path, mut transform)| {
+        let translation = transform.translation.truncate();
+        let origin = *path.origin.get_or_insert(translation);
+        let Some(point) = path.points.get(path.next).map(|point| origin + *point) else {
+            body.delta = Vec2::ZERO;
+            return;
+        };
+
+        let to_point = point - translation;
+        let step = body.speed * FIXED_TIMESTEP;
+        let delta = if to_point.length() <= step {
+            path.next = (path.next + 1) % path.points.len();
+            to_point
+        } else {
+            to_point.normalize() * step
+        };
+        transform.translation += delta.extend(0.0);
+        body.delta = delta;
+    });
+}
+
+/// Move grounded players along with the platform under their feet, airborne players keep their
+/// own momentum
+pub fn carry_grounded_players(
+    q_platform: Query<&MovingBody, With<Platform>>,
+    q_foot: Query<Entity, (With<PlayerFoot>, With<Sensor>)>,
+    q_children: Query<&Children>,
+    mut q_player: Query<(Entity, &PlayerInfo, &mut Transform), With<Player>>,
+    rapier_context: Res<RapierContext>,
+) {
+    if q_platform.is_empty() {
+        return;
+    }
+    q_player.for_each_mut(|(player, info, mut transform)| {
+        if !info.is_grounded {
+            return;
+        }
+        let delta = q_children
+            .iter_descendants(player)
+            .filter(|child| q_foot.contains(*child))
+            .flat_map(|foot| {
+                rapier_context
+                    .intersections_with(foot)
+                    .filter(|(_, _, intersecting)| *intersecting)
+                    .map(move |(entity1, entity2, _)| {
+                        (entity1 == foot).then_some(entity2).unwrap_or(entity1)
+                    })
+            })
+            .find_map(|other| q_platform.get(other).ok())
+            .map(|body| body.delta);
+        if let Some(delta) = delta {
+            transform.translation += delta.extend(0.0);
+        }
+    });
+}
+
+/// Drop hanging spikes on players walking under them, they land on the first solid thing below
+/// and hang back up after a while
+pub fn drop_falling_hazards(
+    mut q_hazard: Query<(
+        Entity,
+        &mut FallingHazard,
+        &mut Transform,
+        &GlobalTransform,
+        &Collider,
+    )>,
+    q_player: Query<&GlobalTransform, With<Player>>,
+    q_platform: Query<(), With<Platform>>,
+    rapier_context: Res<RapierContext>,
+    mut evw_hazard: EventWriter<FallingHazardEvent>,
+) {
+    let is_solid = |entity: Entity| q_platform.contains(entity);
+    q_hazard.for_each_mut(
+        |(entity, mut hazard, mut transform, global_transform, collider)| {
+            let origin = *hazard
+                .origin
+                .get_or_insert(transform.translation.truncate());
+            let translation = global_transform.translation().truncate();
+
+            let next_state = match hazard.state {
+                FallingHazardState::Hanging => {
+                    let half_width = collider
+                        .as_cuboid()
+                        .map_or(TILE_HALF_SIZE.0, |cuboid| cuboid.half_extents().x);
+                    let player_below = q_player.iter().any(|player_transform| {
+                        let below = translation - player_transform.translation().truncate();
+                        below.x.abs() <= half_width
+                            && below.y > 0.0
+                            && below.y <= hazard.trigger_depth
+                    });
+                    if !player_below {
+                        return;
+                    }
+                    evw_hazard.send(FallingHazardEvent::Dropped(entity));
+                    FallingHazardState::Falling { speed: 0.0 }
+                }
+                FallingHazardState::Falling { speed } => {
+                    let speed = (speed + hazard.gravity * FIXED_TIMESTEP).min(hazard.max_speed);
+                    let step = speed * FIXED_TIMESTEP;
+                    let filter = QueryFilter::new()
+                        .exclude_sensors()
+                        .exclude_collider(entity)
+                        .predicate(&is_solid);
+                    match rapier_context.cast_shape(
+                        translation,
+                        0.0,
+                        -step * Vec2::Y,
+                        collider,
+                        1.0,
+                        filter,
+                    ) {
+                        Some((_, toi)) => {
+                            transform.translation.y -= step * toi.toi;
+                            evw_hazard.send(FallingHazardEvent::Landed(entity));
+                            FallingHazardState::Landed {
+                                remaining: hazard.reset_delay,
+                            }
+                        }
+                        None => {
+                            transform.translation.y -= step;
+                            FallingHazardState::Falling { speed }
+                        }
+                    }
+                }
+                FallingHazardState::Landed { remaining } if remaining > FIXED_TIMESTEP => {
+                    FallingHazardState::Landed {
+                        remaining: remaining - FIXED_TIMESTEP,
+                    }
+                }
+                FallingHazardState::Landed { .. } => {
+                    transform.translation = origin.extend(transform.translation.z);
+                    evw_hazard.send(FallingHazardEvent::Reset(entity));
+                    FallingHazardState::Hanging
+                }
+            };
+            hazard.state = next_state;
+        },
+    );
+}
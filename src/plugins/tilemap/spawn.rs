@@ -1,14 +1,23 @@
 use std::collections::{HashMap, HashSet};
 
-use crate::data::physics::{CollisionGroupsFilter, GameCollisionGroups};
-use crate::data::tilemap::*;
-use crate::data::tilemap::{TerrainTile, TrapTile, WebStickerTile};
-use bevy::prelude::*;
-use bevy_ecs_ldtk::{prelude::*, GridCoords};
+use crate::data::physics::{ColliderBundle, CollisionGroupsFilter, GameCollisionGroups};
+use crate::data::tilemap::{
+    IntGridRow, IntGridTile, SpikeDirection, SurfaceMaterial, TileColliders, TileKind, Trap,
+    TrapBundle,
+};
+use bevy::{ecs::system::EntityCommands, prelude::*};
+use bevy_ecs_ldtk::{ldtk::FieldValue, prelude::*, GridCoords};
 use bevy_rapier2d::prelude::*;
 
-pub fn spawn_tilemap(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let ldtk_handle = asset_server.load(super::MAP_PATH);
+/// Tiles with colliders of their own are drawn on top of the layers they're placed in
+const TILE_SPRITE_Z: f32 = 10.0;
+
+pub fn spawn_tilemap(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    map_path: Res<super::MapPath>,
+) {
+    let ldtk_handle = asset_server.load(map_path.0);
     commands
         .spawn(LdtkWorldBundle {
             ldtk_handle,
@@ -17,82 +26,285 @@ pub fn spawn_tilemap(mut commands: Commands, asset_server: Res<AssetServer>) {
         .insert(Name::from("LDtk World"));
 }
 
+/// Give new int-grid tiles the colliders and components of their row in
+/// [`super::INT_GRID_TILES`]
 pub fn spawn_tile_colliders(
     mut commands: Commands,
-    terrain_query: Query<(&GridCoords, &Parent), Added<TerrainTile>>,
-    metal_query: Query<(&GridCoords, &Parent), Added<WebStickerTile>>,
-    trap_query: Query<(&GridCoords, &Parent), Added<TrapTile>>,
-    parent_query: Query<
-        &Parent,
-        (
-            Without<TerrainTile>,
-            Without<WebStickerTile>,
-            Without<TrapTile>,
-        ),
-    >,
+    tile_query: Query<(Entity, &TileKind, &IntGridRow, &GridCoords, &Parent), Added<TileKind>>,
+    parent_query: Query<&Parent, Without<TileKind>>,
     level_query: Query<(Entity, &Handle<LdtkLevel>)>,
     levels: Res<Assets<LdtkLevel>>,
 ) {
-    let mut level_to_terrain_tile_coords: HashMap<Entity, HashSet<GridCoords>> = HashMap::new();
-    let mut level_to_metal_tile_coords: HashMap<Entity, HashSet<GridCoords>> = HashMap::new();
-    let mut level_to_trap_tile_coords: HashMap<Entity, HashSet<GridCoords>> = HashMap::new();
-
-    terrain_query.for_each(|(&grid_coords, parent)| {
-        if let Ok(grandparent) = parent_query.get(parent.get()) {
-            level_to_terrain_tile_coords
-                .entry(grandparent.get())
-                .or_default()
-                .insert(grid_coords);
+    // Tiles of one kind are merged across rows, the plugin checks that those rows agree
+    let mut kind_to_level_tile_coords: HashMap<
+        TileKind,
+        (IntGridRow, HashMap<Entity, HashSet<GridCoords>>),
+    > = HashMap::new();
+
+    tile_query.for_each(|(tile, &kind, &row, &grid_coords, parent)| {
+        let IntGridTile {
+            colliders, insert, ..
+        } = *row.tile();
+        match colliders {
+            TileColliders::Merged { .. }
+            | TileColliders::PerTile { .. }
+            | TileColliders::Spike { .. } => {
+                if let Ok(grandparent) = parent_query.get(parent.get()) {
+                    kind_to_level_tile_coords
+                        .entry(kind)
+                        .or_insert_with(|| (row, HashMap::new()))
+                        .1
+                        .entry(grandparent.get())
+                        .or_default()
+                        .insert(grid_coords);
+                }
+            }
+            TileColliders::Sensor => {
+                let mut tile = commands.entity(tile);
+                tile.insert((
+                    ColliderBundle {
+                        collider: Collider::cuboid(
+                            super::TILE_HALF_SIZE.0,
+                            super::TILE_HALF_SIZE.1,
+                        ),
+                        ..default()
+                    },
+                    Sensor,
+                ));
+                insert(&mut tile);
+            }
+            TileColliders::None => insert(&mut commands.entity(tile)),
         }
     });
-    metal_query.for_each(|(&grid_coords, parent)| {
-        if let Ok(grandparent) = parent_query.get(parent.get()) {
-            level_to_metal_tile_coords
-                .entry(grandparent.get())
-                .or_default()
-                .insert(grid_coords);
+
+    // Spawn in a fixed order so physics plays out the same in replays
+    let mut kind_to_level_tile_coords: Vec<_> = kind_to_level_tile_coords.into_iter().collect();
+    kind_to_level_tile_coords.sort_by_key(|(kind, _)| *kind);
+    for (_, (row, level_to_tile_coords)) in kind_to_level_tile_coords {
+        let IntGridTile {
+            colliders, insert, ..
+        } = *row.tile();
+        match colliders {
+            TileColliders::Merged { groups, material } => spawn_connected_colliders(
+                &level_to_tile_coords,
+                &level_query,
+                &levels,
+                insert,
+                groups,
+                material,
+                &mut commands,
+            ),
+            // These come and go one by one, merging them would take neighbours down with them
+            TileColliders::PerTile {
+                groups,
+                material,
+                color,
+            } => spawn_single_tile_colliders(
+                &level_to_tile_coords,
+                &level_query,
+                &levels,
+                insert,
+                groups,
+                material,
+                color,
+                &mut commands,
+            ),
+            TileColliders::Spike { direction, damage } => spawn_spike_colliders(
+                &level_to_tile_coords,
+                &level_query,
+                &levels,
+                insert,
+                direction,
+                damage,
+                &mut commands,
+            ),
+            TileColliders::Sensor | TileColliders::None => (),
         }
-    });
-    trap_query.for_each(|(&grid_coords, parent)| {
-        if let Ok(grandparent) = parent_query.get(parent.get()) {
-            level_to_trap_tile_coords
-                .entry(grandparent.get())
-                .or_default()
-                .insert(grid_coords);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_single_tile_colliders(
+    level_to_tile_coords: &HashMap<Entity, HashSet<GridCoords>>,
+    level_query: &Query<(Entity, &Handle<LdtkLevel>)>,
+    levels: &Assets<LdtkLevel>,
+    insert: fn(&mut EntityCommands),
+    tile_collision_groups: Group,
+    material: SurfaceMaterial,
+    color: Color,
+    commands: &mut Commands,
+) {
+    level_query.for_each(|(level_entity, level_handle)| {
+        if let Some(level_tiles) = level_to_tile_coords.get(&level_entity) {
+            let grid_size = levels
+                .get(level_handle)
+                .expect("Level should be loaded by this point")
+                .level
+                .layer_instances
+                .as_ref()
+                .expect("Level asset should have layers")[0]
+                .grid_size as f32;
+            // Spawn in a fixed order so physics plays out the same in replays
+            let mut level_tiles: Vec<&GridCoords> = level_tiles.iter().collect();
+            level_tiles.sort_by_key(|grid_coords| (grid_coords.y, grid_coords.x));
+
+            commands.entity(level_entity).with_children(|level| {
+                for grid_coords in level_tiles {
+                    let mut tile = level
+                        .spawn(SpriteBundle {
+                            sprite: Sprite {
+                                color,
+                                custom_size: Some(Vec2::splat(grid_size)),
+                                ..default()
+                            },
+                            transform: Transform::from_xyz(
+                                (grid_coords.x as f32 + 0.5) * grid_size,
+                                (grid_coords.y as f32 + 0.5) * grid_size,
+                                TILE_SPRITE_Z,
+                            ),
+                            ..default()
+                        })
+                        .insert(Collider::cuboid(grid_size / 2., grid_size / 2.))
+                        .insert(CollisionGroups {
+                            memberships: tile_collision_groups,
+                            filters: tile_collision_groups.filter_group(),
+                        })
+                        .insert(RigidBody::Fixed)
+                        .insert(Friction::new(material.friction))
+                        .insert(Restitution {
+                            coefficient: material.restitution,
+                            combine_rule: CoefficientCombineRule::Max,
+                        })
+                        .insert(material);
+                    insert(&mut tile);
+                }
+            });
         }
     });
-    spawn_connected_colliders::<TerrainBundle>(
-        &level_to_terrain_tile_coords,
-        &level_query,
-        &levels,
-        GameCollisionGroups::NON_STICK_TERRAIN,
-        &mut commands,
-    );
-    spawn_connected_colliders::<WebStickerBundle>(
-        &level_to_metal_tile_coords,
-        &level_query,
-        &levels,
-        GameCollisionGroups::WEB_STICKABLE_TERRAIN,
-        &mut commands,
-    );
-    spawn_connected_colliders::<TrapBundle>(
-        &level_to_trap_tile_coords,
-        &level_query,
-        &levels,
-        GameCollisionGroups::TRAP,
-        &mut commands,
-    );
 }
 
-fn spawn_connected_colliders<T>(
+/// Spikes only hurt from their pointed side, so their colliders start at the base of the spike
+/// and take its shape. Levels with [`super::MERGE_SPIKES_FIELD`] set trade the triangles for
+/// fewer colliders, strips half a tile deep along the base of neighbouring spikes.
+fn spawn_spike_colliders(
     level_to_tile_coords: &HashMap<Entity, HashSet<GridCoords>>,
     level_query: &Query<(Entity, &Handle<LdtkLevel>)>,
     levels: &Assets<LdtkLevel>,
+    insert: fn(&mut EntityCommands),
+    direction: SpikeDirection,
+    damage: i32,
+    commands: &mut Commands,
+) {
+    let normal = direction.normal();
+    let across = normal.perp().abs();
+
+    level_query.for_each(|(level_entity, level_handle)| {
+        let Some(level_tiles) = level_to_tile_coords.get(&level_entity) else {
+            return;
+        };
+        let level = &levels
+            .get(level_handle)
+            .expect("Level should be loaded by this point")
+            .level;
+        let grid_size = level
+            .layer_instances
+            .as_ref()
+            .expect("Level asset should have layers")[0]
+            .grid_size as f32;
+        let half = grid_size / 2.0;
+        let merge = level.field_instances.iter().any(|field| {
+            field.identifier == super::MERGE_SPIKES_FIELD
+                && matches!(field.value, FieldValue::Bool(true))
+        });
+
+        // Collider and the middle of its base, in level space
+        let spikes: Vec<(Collider, Vec2)> = if merge {
+            // Line the spike's base is on and its position along it
+            let along_x = across.x > 0.0;
+            let mut cells: Vec<(i32, i32)> = level_tiles
+                .iter()
+                .map(|coords| {
+                    along_x
+                        .then_some((coords.y, coords.x))
+                        .unwrap_or((coords.x, coords.y))
+                })
+                .collect();
+            cells.sort();
+
+            let mut runs: Vec<(i32, i32, i32)> = Vec::new();
+            for (line, position) in cells {
+                match runs.last_mut() {
+                    Some((last_line, _, end)) if *last_line == line && *end + 1 == position => {
+                        *end = position;
+                    }
+                    _ => runs.push((line, position, position)),
+                }
+            }
+
+            runs.into_iter()
+                .map(|(line, start, end)| {
+                    let along = (start + end + 1) as f32 * half;
+                    let line = (line as f32 + 0.5) * grid_size;
+                    let center = along_x
+                        .then_some(Vec2::new(along, line))
+                        .unwrap_or(Vec2::new(line, along));
+                    let half_extents =
+                        across * (end - start + 1) as f32 * half + normal.abs() * half / 2.0;
+                    let strip = Collider::cuboid(half_extents.x, half_extents.y);
+                    (
+                        Collider::compound(vec![(normal * half / 2.0, 0.0, strip)]),
+                        center - normal * half,
+                    )
+                })
+                .collect()
+        } else {
+            let mut level_tiles: Vec<&GridCoords> = level_tiles.iter().collect();
+            level_tiles.sort_by_key(|grid_coords| (grid_coords.y, grid_coords.x));
+
+            level_tiles
+                .into_iter()
+                .map(|grid_coords| {
+                    let center =
+                        (Vec2::new(grid_coords.x as f32, grid_coords.y as f32) + 0.5) * grid_size;
+                    (
+                        Collider::triangle(-across * half, across * half, normal * grid_size),
+                        center - normal * half,
+                    )
+                })
+                .collect()
+        };
+
+        commands.entity(level_entity).with_children(|level| {
+            for (collider, base) in spikes {
+                let mut spike = level.spawn((
+                    collider,
+                    CollisionGroups {
+                        memberships: GameCollisionGroups::TRAP,
+                        filters: GameCollisionGroups::TRAP.filter_group(),
+                    },
+                    RigidBody::Fixed,
+                    Friction::new(1.0),
+                    TransformBundle::from_transform(Transform::from_translation(base.extend(0.0))),
+                    TrapBundle::new(Trap {
+                        damage,
+                        points: Some(normal),
+                    }),
+                ));
+                insert(&mut spike);
+            }
+        });
+    });
+}
+
+fn spawn_connected_colliders(
+    level_to_tile_coords: &HashMap<Entity, HashSet<GridCoords>>,
+    level_query: &Query<(Entity, &Handle<LdtkLevel>)>,
+    levels: &Assets<LdtkLevel>,
+    insert: fn(&mut EntityCommands),
     tile_collision_groups: Group,
+    material: SurfaceMaterial,
     commands: &mut Commands,
-) where
-    T: Bundle + Default,
-{
+) {
     #[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
     struct Plate {
         left: i32,
@@ -175,8 +387,8 @@ fn spawn_connected_colliders<T>(
 
             commands.entity(level_entity).with_children(|level| {
                 for tile_rect in tile_rects {
-                    level
-                        .spawn_empty()
+                    let mut collider = level.spawn_empty();
+                    collider
                         .insert(Collider::cuboid(
                             (tile_rect.right as f32 - tile_rect.left as f32 + 1.)
                                 * grid_size as f32
@@ -190,14 +402,19 @@ fn spawn_connected_colliders<T>(
                             filters: tile_collision_groups.filter_group(),
                         })
                         .insert(RigidBody::Fixed)
-                        .insert(Friction::new(1.0))
+                        .insert(Friction::new(material.friction))
+                        .insert(Restitution {
+                            coefficient: material.restitution,
+                            combine_rule: CoefficientCombineRule::Max,
+                        })
+                        .insert(material)
                         .insert(Transform::from_xyz(
                             (tile_rect.left + tile_rect.right + 1) as f32 * grid_size as f32 / 2.,
                             (tile_rect.bottom + tile_rect.top + 1) as f32 * grid_size as f32 / 2.,
                             0.,
                         ))
-                        .insert(GlobalTransform::default())
-                        .insert(T::default());
+                        .insert(GlobalTransform::default());
+                    insert(&mut collider);
                 }
             });
         }
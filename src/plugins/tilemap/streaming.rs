@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{ldtk::Level, prelude::*};
+use bevy_rapier2d::prelude::Collider;
+
+use super::{LevelStats, LevelStreaming};
+
+/// Keep the levels within [`LevelStreaming::radius`] of the selected one spawned, closest first
+/// up to [`LevelStreaming::max_levels`]. Runs right after the LDtk plugin picked the selected
+/// level and its neighbours, levels left out are despawned along with their tiles and colliders.
+pub fn stream_levels(
+    level_selection: Option<Res<LevelSelection>>,
+    streaming: Res<LevelStreaming>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    mut q_world: Query<(&Handle<LdtkAsset>, &mut LevelSet)>,
+) {
+    let Some(level_selection) = level_selection else {
+        return;
+    };
+    q_world.for_each_mut(|(ldtk_handle, mut level_set)| {
+        let Some(ldtk_asset) = ldtk_assets.get(ldtk_handle) else {
+            return;
+        };
+        let levels = &ldtk_asset.project.levels;
+        let Some(selected) = levels
+            .iter()
+            .enumerate()
+            .find(|(index, level)| level_selection.is_match(index, level))
+            .map(|(_, level)| level)
+        else {
+            return;
+        };
+
+        let selected_bounds = world_bounds(selected);
+        let mut nearby: Vec<(f32, &Level)> = levels
+            .iter()
+            .filter(|level| level.iid != selected.iid)
+            .map(|level| (distance(selected_bounds, world_bounds(level)), level))
+            .filter(|(distance, _)| *distance <= streaming.radius)
+            .collect();
+        nearby.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        let iids: HashSet<String> = std::iter::once(selected)
+            .chain(nearby.into_iter().map(|(_, level)| level))
+            .take(streaming.max_levels.max(1))
+            .map(|level| level.iid.clone())
+            .collect();
+        if level_set.iids != iids {
+            level_set.iids = iids;
+        }
+    });
+}
+
+/// Count what the spawned levels hold and log it whenever it changes
+pub fn count_level_entities(
+    q_level: Query<Entity, With<Handle<LdtkLevel>>>,
+    q_children: Query<&Children>,
+    q_collider: Query<(), With<Collider>>,
+    mut stats: ResMut<LevelStats>,
+) {
+    let mut counted = LevelStats::default();
+    q_level.for_each(|level| {
+        counted.levels += 1;
+        q_children.iter_descendants(level).for_each(|entity| {
+            counted.entities += 1;
+            if q_collider.contains(entity) {
+                counted.colliders += 1;
+            }
+        });
+    });
+
+    if *stats != counted {
+        info!(
+            "{} levels spawned with {} entities and {} colliders",
+            counted.levels, counted.entities, counted.colliders
+        );
+        *stats = counted;
+    }
+}
+
+/// Bounds of the level in the LDtk world, y pointing down
+fn world_bounds(level: &Level) -> Rect {
+    let min = Vec2::new(level.world_x as f32, level.world_y as f32);
+    Rect::from_corners(
+        min,
+        min + Vec2::new(level.px_wid as f32, level.px_hei as f32),
+    )
+}
+
+/// Gap between the two levels, 0 when they touch or overlap
+fn distance(a: Rect, b: Rect) -> f32 {
+    let gap = (a.min - b.max).max(b.min - a.max).max(Vec2::ZERO);
+    gap.length()
+}
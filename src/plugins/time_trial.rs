@@ -0,0 +1,146 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    plugins::{
+        physics::{FixedUpdateStage, FIXED_TIMESTEP},
+        tilemap::LEVELS,
+    },
+    utils::state_helper::StateExtend,
+    GameState,
+};
+
+mod ghost;
+mod splits;
+
+const TIME_TRIALS_DIR: &str = "time_trials";
+const GHOST_ALPHA: f32 = 0.35;
+const SPLIT_TEXT_TIME: f32 = 3.0;
+
+/// Where the player was and which sprite it showed on one tick
+#[derive(Debug, Clone, Copy)]
+pub struct GhostFrame {
+    pub translation: Vec3,
+    pub rotation: f32,
+    pub sprite_index: usize,
+    /// The player faces right with its sprite flipped
+    pub flip_x: bool,
+}
+
+/// One run through a level, from entering it to entering the next one
+#[derive(Debug, Default, Clone)]
+pub struct LevelRun {
+    pub ticks: u32,
+    pub frames: Vec<GhostFrame>,
+}
+
+impl LevelRun {
+    pub fn seconds(&self) -> f32 {
+        self.ticks as f32 * FIXED_TIMESTEP
+    }
+
+    fn path(level: usize) -> PathBuf {
+        Path::new(TIME_TRIALS_DIR).join(format!("{}.txt", LEVELS[level]))
+    }
+
+    /// The tick count on the first line then one frame per line
+    pub fn save(&self, level: usize) -> io::Result<()> {
+        let mut text = format!("{}\n", self.ticks);
+        self.frames.iter().for_each(|frame| {
+            text.push_str(&format!(
+                "{} {} {} {} {} {}\n",
+                frame.translation.x,
+                frame.translation.y,
+                frame.translation.z,
+                frame.rotation,
+                frame.sprite_index,
+                frame.flip_x
+            ));
+        });
+        fs::create_dir_all(TIME_TRIALS_DIR)?;
+        fs::write(Self::path(level), text)
+    }
+
+    /// `None` when the level was never finished or the file can't be read
+    pub fn load(level: usize) -> Option<Self> {
+        let text = fs::read_to_string(Self::path(level)).ok()?;
+        let mut lines = text.lines();
+        let ticks = lines.next()?.trim().parse().ok()?;
+        let frames = lines
+            .map(|line| {
+                let mut words = line.split_whitespace();
+                let mut float = || words.next()?.parse::<f32>().ok();
+                let translation = Vec3::new(float()?, float()?, float()?);
+                let rotation = float()?;
+                let sprite_index = words.next()?.parse().ok()?;
+                // Runs saved before the facing was recorded face the default side
+                let flip_x = words.next().map_or(Some(false), |word| word.parse().ok())?;
+                Some(GhostFrame {
+                    translation,
+                    rotation,
+                    sprite_index,
+                    flip_x,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self { ticks, frames })
+    }
+}
+
+/// Timing of the level the player is in and the best run of every level
+#[derive(Resource, Debug, Default)]
+pub struct TimeTrials {
+    /// `None` until the player spawned
+    pub level: Option<usize>,
+    pub current: LevelRun,
+    pub best: HashMap<usize, LevelRun>,
+}
+
+impl TimeTrials {
+    pub fn load() -> Self {
+        Self {
+            best: (0..LEVELS.len())
+                .filter_map(|level| LevelRun::load(level).map(|run| (level, run)))
+                .collect(),
+            ..default()
+        }
+    }
+}
+
+const TICK_END: FixedUpdateStage = FixedUpdateStage::ResolveComplexForces;
+
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum TimeTrialSystem {
+    TrackLevelTime,
+    UpdateGhost,
+}
+
+pub struct TimeTrialPlugin {
+    run_in: Option<GameState>,
+}
+impl TimeTrialPlugin {
+    pub fn new(run_in: Option<GameState>) -> Self {
+        Self { run_in }
+    }
+}
+
+impl Plugin for TimeTrialPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TimeTrials::load())
+            .add_system_run_if(self.run_in, splits::fade_split_texts);
+        // The ghost shows the frame of its run recorded on the tick just counted
+        app.add_fixed_chain_run_if(self.run_in, TICK_END)
+            .then(TimeTrialSystem::TrackLevelTime, splits::track_level_time)
+            .then(TimeTrialSystem::UpdateGhost, ghost::update_ghost);
+        if let Some(state) = self.run_in {
+            // The player respawns at the start when coming back from the menu
+            app.add_exit_system(state, splits::reset_level_time);
+        }
+    }
+}
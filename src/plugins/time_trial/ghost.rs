@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+
+use crate::data::{player::Player, time_trial::Ghost};
+
+use super::{TimeTrials, GHOST_ALPHA};
+
+/// Play the best run of the current level back on a ghost, in step with the time spent in the
+/// level. The ghost hides once its run is over and goes away when the player changes level.
+pub fn update_ghost(
+    trials: Res<TimeTrials>,
+    q_player: Query<(&Player, &Handle<TextureAtlas>, &TextureAtlasSprite)>,
+    mut q_ghost: Query<
+        (
+            Entity,
+            &Ghost,
+            &mut Transform,
+            &mut TextureAtlasSprite,
+            &mut Visibility,
+        ),
+        Without<Player>,
+    >,
+    mut commands: Commands,
+) {
+    let best = trials
+        .level
+        .and_then(|level| trials.best.get(&level).map(|run| (level, run)));
+
+    let mut has_ghost = false;
+    q_ghost.for_each_mut(|(entity, ghost, mut transform, mut sprite, mut visibility)| {
+        let Some((_, run)) = best.filter(|(level, _)| *level == ghost.level) else {
+            commands.entity(entity).despawn_recursive();
+            return;
+        };
+        has_ghost = true;
+
+        // The current run's frame of this tick was just recorded as its last one
+        let frame = (trials.current.ticks as usize)
+            .checked_sub(1)
+            .and_then(|tick| run.frames.get(tick));
+        visibility.is_visible = frame.is_some();
+        if let Some(frame) = frame {
+            // Behind the player
+            transform.translation = frame.translation - Vec3::Z;
+            transform.rotation = Quat::from_rotation_z(frame.rotation);
+            sprite.index = frame.sprite_index;
+            sprite.flip_x = frame.flip_x;
+        }
+    });
+
+    let first_player = q_player.iter().find(|(player, _, _)| player.index == 0);
+    let (false, Some((level, _)), Some((_, texture_atlas, player_sprite))) =
+        (has_ghost, best, first_player)
+    else {
+        return;
+    };
+    commands
+        .spawn(SpriteSheetBundle {
+            texture_atlas: texture_atlas.clone(),
+            sprite: TextureAtlasSprite {
+                color: Color::rgba(1.0, 1.0, 1.0, GHOST_ALPHA),
+                custom_size: player_sprite.custom_size,
+                ..default()
+            },
+            visibility: Visibility::INVISIBLE,
+            ..default()
+        })
+        .insert(Ghost { level })
+        .insert(Name::from("Ghost"));
+}
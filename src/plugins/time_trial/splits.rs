@@ -0,0 +1,169 @@
+use bevy::{prelude::*, text::Text2dBounds};
+use bevy_ecs_ldtk::LevelSelection;
+
+use crate::{
+    data::{player::Player, tilemap::Credits, time_trial::SplitText},
+    plugins::tilemap::{
+        current_level_index, FontHandle, LevelChanged, CREDITS_LEVEL_INDEX, LEVELS,
+    },
+};
+
+use super::{GhostFrame, LevelRun, TimeTrials, SPLIT_TEXT_TIME};
+
+const SPLIT_TEXT_COLOR: Color = Color::rgb(0.95, 0.95, 0.95);
+
+fn level_name(level: usize) -> String {
+    LEVELS[level].replace('_', " ")
+}
+
+/// Count the ticks spent in the current level and record the ghost frames along the way.
+/// Moving on to a later level finishes the one left behind, going back just restarts the timer.
+pub fn track_level_time(
+    mut trials: ResMut<TimeTrials>,
+    level_selection: Res<LevelSelection>,
+    font_handle: Res<FontHandle>,
+    q_player: Query<(&Player, &Transform, &TextureAtlasSprite)>,
+    q_cred: Query<&GlobalTransform, With<Credits>>,
+    mut evr_level_changed: EventReader<LevelChanged>,
+    mut commands: Commands,
+) {
+    // Runs are timed on the first player, the level follows that one
+    let Some((_, player_transform, player_sprite)) =
+        q_player.iter().find(|(player, _, _)| player.index == 0)
+    else {
+        return;
+    };
+
+    for LevelChanged { previous, current } in evr_level_changed.iter() {
+        if current > previous && trials.level == Some(*previous) {
+            let run = std::mem::take(&mut trials.current);
+            let best = trials.best.get(previous).map(LevelRun::seconds);
+            let is_best = best.map_or(true, |best| run.seconds() < best);
+            let split = match best {
+                Some(best) if !is_best => format!("best {:.2}s", best),
+                _ => String::from("new best!"),
+            };
+            spawn_text(
+                &mut commands,
+                &font_handle,
+                format!(
+                    "{}  {:.2}s  {}",
+                    level_name(*previous),
+                    run.seconds(),
+                    split
+                ),
+                player_transform.translation + 24.0 * Vec3::Y,
+                TextAlignment::CENTER,
+            )
+            .insert(SplitText {
+                timer: Timer::from_seconds(SPLIT_TEXT_TIME, TimerMode::Once),
+            });
+
+            if is_best {
+                if let Err(err) = run.save(*previous) {
+                    error!("Failed to save the {} run: {}", LEVELS[*previous], err);
+                }
+                trials.best.insert(*previous, run);
+            }
+        }
+        trials.level = Some(*current);
+        trials.current = LevelRun::default();
+
+        if *current == CREDITS_LEVEL_INDEX {
+            if let Ok(cred_transform) = q_cred.get_single() {
+                spawn_best_splits(&mut commands, &font_handle, &trials, cred_transform);
+            }
+        }
+    }
+
+    if trials.level.is_none() {
+        trials.level = current_level_index(&level_selection);
+    }
+    trials.current.ticks += 1;
+    trials.current.frames.push(GhostFrame {
+        translation: player_transform.translation,
+        rotation: player_transform.rotation.to_euler(EulerRot::ZYX).0,
+        sprite_index: player_sprite.index,
+        flip_x: player_sprite.flip_x,
+    });
+}
+
+/// List the best time of every level above the credits, with the sum of them once every level
+/// has been finished
+fn spawn_best_splits(
+    commands: &mut Commands,
+    font_handle: &FontHandle,
+    trials: &TimeTrials,
+    cred_transform: &GlobalTransform,
+) {
+    let bests: Vec<_> = (0..CREDITS_LEVEL_INDEX)
+        .map(|level| trials.best.get(&level).map(LevelRun::seconds))
+        .collect();
+    let mut lines: Vec<String> = bests
+        .iter()
+        .enumerate()
+        .map(|(level, best)| match best {
+            Some(best) => format!("{}  {:.2}s", level_name(level), best),
+            None => format!("{}  --", level_name(level)),
+        })
+        .collect();
+    if let Some(total) = bests.iter().copied().sum::<Option<f32>>() {
+        lines.push(format!("Total  {:.2}s", total));
+    }
+
+    spawn_text(
+        commands,
+        font_handle,
+        lines.join("\n"),
+        cred_transform.translation() + 40.0 * Vec3::Y,
+        TextAlignment::BOTTOM_RIGHT,
+    )
+    .insert(Name::from("Best splits"));
+}
+
+fn spawn_text<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    font_handle: &FontHandle,
+    text: String,
+    translation: Vec3,
+    alignment: TextAlignment,
+) -> bevy::ecs::system::EntityCommands<'w, 's, 'a> {
+    commands.spawn(Text2dBundle {
+        text: Text::from_section(
+            text,
+            TextStyle {
+                font: font_handle.0.clone(),
+                font_size: 14.0,
+                color: SPLIT_TEXT_COLOR,
+            },
+        )
+        .with_alignment(alignment),
+        text_2d_bounds: Text2dBounds {
+            size: Vec2::new(300.0, 200.0),
+        },
+        transform: Transform::from_translation(translation).with_scale(0.5 * Vec3::ONE),
+        ..default()
+    })
+}
+
+pub fn fade_split_texts(
+    time: Res<Time>,
+    mut q_split: Query<(Entity, &mut SplitText, &mut Text)>,
+    mut commands: Commands,
+) {
+    q_split.for_each_mut(|(entity, mut split, mut text)| {
+        split.timer.tick(time.delta());
+        let alpha = 1.0 - split.timer.percent();
+        text.sections.iter_mut().for_each(|section| {
+            section.style.color.set_a(alpha);
+        });
+        split.timer.finished().then(|| {
+            commands.entity(entity).despawn_recursive();
+        });
+    });
+}
+
+pub fn reset_level_time(mut trials: ResMut<TimeTrials>) {
+    trials.level = None;
+    trials.current = LevelRun::default();
+}
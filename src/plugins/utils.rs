@@ -7,16 +7,24 @@ pub fn cursor_screen_to_world(
     camera: &Camera,
     camera_transform: &GlobalTransform,
 ) -> Option<Vec2> {
-    // get the window that the camera is displaying to (or the primary window)
-    let wnd = wnds.get_primary().unwrap();
+    // get the window that the camera is displaying to (or the primary window), there is none
+    // when running headless
+    let wnd = wnds.get_primary()?;
 
     // check if the cursor is inside the window and get its position
     if let Some(screen_pos) = wnd.cursor_position() {
         // get the size of the window
         let window_size = Vec2::new(wnd.width() as f32, wnd.height() as f32);
 
-        // convert screen position [0..resolution] to ndc [-1..1] (gpu coordinates)
-        let ndc = (screen_pos / window_size) * 2.0 - Vec2::ONE;
+        // the part of the window the camera draws to, smaller than the window with split
+        // cameras. Viewports start from the top left while the cursor starts from the bottom left
+        let (viewport_min, viewport_size) = match camera.logical_viewport_rect() {
+            Some((min, max)) => (Vec2::new(min.x, window_size.y - max.y), max - min),
+            None => (Vec2::ZERO, window_size),
+        };
+
+        // convert viewport position [0..resolution] to ndc [-1..1] (gpu coordinates)
+        let ndc = ((screen_pos - viewport_min) / viewport_size) * 2.0 - Vec2::ONE;
 
         // matrix for undoing the projection and camera transform
         let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix().inverse();
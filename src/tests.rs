@@ -0,0 +1,276 @@
+//! Gameplay plugins running without a window, renderer or audio, on a small LDtk file. Every
+//! update of a [`TestApp`] is exactly one physics tick so tests play out the same on any machine.
+//! Besides `Level_0` and `Level_1`, the test map has a `Test_*` level per fixture, placed apart so
+//! a test started in one with [`TestApp::in_level`] only spawns that one.
+
+use std::{collections::HashSet, thread, time::Duration};
+
+use bevy::{
+    ecs::event::Event,
+    prelude::*,
+    render::{settings::WgpuSettings, RenderPlugin},
+    sprite::SpritePlugin,
+    time::TimePlugin,
+};
+use bevy_ecs_ldtk::{prelude::*, GridCoords};
+use bevy_rapier2d::prelude::Velocity;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    data::{
+        player::{Player, PlayerInfo},
+        tilemap::{TileColliders, TileKind},
+    },
+    plugins::{
+        base::{advance_time_by_one_tick, CameraPlugin},
+        enemy::EnemyPlugin,
+        physics::PhysicsPlugin,
+        player::{GameMode, PlayerControl, PlayerInput, PlayerPlugin},
+        tilemap::{TilemapPlugin, INT_GRID_TILES, TILE_HALF_SIZE},
+    },
+    GameState,
+};
+
+mod enemy;
+mod physics;
+mod platform;
+mod player;
+mod replay;
+mod tilemap;
+
+const TEST_MAP_PATH: &str = "tests/test.ldtk";
+/// Ticks to wait for the test map to load before giving up
+const LOAD_TIMEOUT: u32 = 600;
+
+pub const TILE_SIZE: f32 = 2.0 * TILE_HALF_SIZE.0;
+
+pub struct TestApp {
+    pub app: App,
+    /// Input given to each player on every tick, by player index
+    pub inputs: Vec<PlayerInput>,
+}
+
+impl TestApp {
+    pub fn new(game_mode: GameMode) -> Self {
+        Self::with_players(game_mode, 1)
+    }
+
+    pub fn with_players(game_mode: GameMode, player_count: usize) -> Self {
+        let mut app = App::new();
+        app.insert_resource(WgpuSettings {
+            backends: None,
+            ..default()
+        })
+        .add_plugins(MinimalPlugins.build().disable::<TimePlugin>())
+        .init_resource::<Time>()
+        .add_system_to_stage(CoreStage::First, advance_time_by_one_tick)
+        .add_plugin(TransformPlugin)
+        .add_plugin(HierarchyPlugin)
+        .add_plugin(InputPlugin)
+        .add_plugin(WindowPlugin {
+            add_primary_window: false,
+            exit_on_all_closed: false,
+            ..default()
+        })
+        .add_plugin(AssetPlugin::default())
+        // Only for the asset and component types, there is no renderer without backends
+        .add_plugin(RenderPlugin)
+        .add_plugin(ImagePlugin::default_nearest())
+        .add_plugin(SpritePlugin)
+        .add_loopless_state(GameState::InGame)
+        .add_plugin(PhysicsPlugin)
+        .add_plugin(CameraPlugin::default())
+        .add_plugin(TilemapPlugin::new(Some(GameState::InGame)).with_map_path(TEST_MAP_PATH))
+        .add_plugin(PlayerPlugin::new(Some(GameState::InGame)).with_player_count(player_count))
+        .add_plugin(EnemyPlugin::new(Some(GameState::InGame)))
+        .insert_resource(game_mode);
+
+        Self {
+            app,
+            inputs: vec![PlayerInput::default(); player_count],
+        }
+    }
+
+    /// Start in this level of the test map instead of the first one
+    pub fn in_level(mut self, identifier: &str) -> Self {
+        self.app
+            .insert_resource(LevelSelection::Identifier(identifier.to_string()));
+        self
+    }
+
+    /// Run the app for this many physics ticks with [`Self::inputs`]
+    pub fn step(&mut self, ticks: u32) {
+        (0..ticks).for_each(|_| {
+            let world = &mut self.app.world;
+            // Players take the test input instead of reading the keyboard
+            let controlled: Vec<Entity> = world
+                .query_filtered::<Entity, With<PlayerControl>>()
+                .iter(world)
+                .collect();
+            controlled.into_iter().for_each(|player| {
+                world.entity_mut(player).remove::<PlayerControl>();
+            });
+            let inputs = &self.inputs;
+            world.query::<(&Player, &mut PlayerInput)>().for_each_mut(
+                world,
+                |(player, mut input)| {
+                    *input = inputs.get(player.index).cloned().unwrap_or_default();
+                },
+            );
+            self.app.update();
+        });
+    }
+
+    /// Step until an event matching the predicate is sent, false if none was within `max_ticks`
+    pub fn step_until_event<E: Event>(
+        &mut self,
+        max_ticks: u32,
+        matches: impl Fn(&E) -> bool,
+    ) -> bool {
+        let mut reader = self.app.world.resource::<Events<E>>().get_reader_current();
+        (0..max_ticks).any(|_| {
+            self.step(1);
+            reader
+                .iter(self.app.world.resource::<Events<E>>())
+                .any(&matches)
+        })
+    }
+
+    /// Step until the test map spawned and its tiles got colliders. Assets load on other
+    /// threads, so this waits a bit between ticks.
+    pub fn wait_for_level(&mut self) {
+        let loaded = (0..LOAD_TIMEOUT).any(|_| {
+            self.step(1);
+            thread::sleep(Duration::from_millis(5));
+            !self.tile_positions(TileKind::Terrain).is_empty()
+        });
+        assert!(loaded, "{} didn't load", TEST_MAP_PATH);
+        // Colliders are spawned the frame after the tiles
+        self.step(2);
+    }
+
+    /// World space centers of every int-grid tile of that kind
+    pub fn tile_positions(&mut self, kind: TileKind) -> Vec<Vec2> {
+        let world = &mut self.app.world;
+        let mut q_tile = world.query::<(&TileKind, &GridCoords, &Parent)>();
+        let mut q_parent = world.query::<&Parent>();
+        let mut q_transform = world.query::<&GlobalTransform>();
+
+        // Tiles are children of their layer, which is a child of the level
+        q_tile
+            .iter(world)
+            .filter(|(tile_kind, _, _)| **tile_kind == kind)
+            .filter_map(|(_, coords, layer)| {
+                let level = q_parent.get(world, layer.get()).ok()?;
+                let level_transform = q_transform.get(world, level.get()).ok()?;
+                let offset = Vec2::new(coords.x as f32 + 0.5, coords.y as f32 + 0.5) * TILE_SIZE;
+                Some(level_transform.translation().truncate() + offset)
+            })
+            .collect()
+    }
+
+    /// Grid cells of every tile the player can collide with
+    pub fn solid_cells(&mut self) -> HashSet<IVec2> {
+        INT_GRID_TILES
+            .iter()
+            .filter(|tile| !matches!(tile.colliders, TileColliders::Sensor | TileColliders::None))
+            .flat_map(|tile| self.tile_positions(tile.kind))
+            .map(cell)
+            .collect()
+    }
+
+    /// Leftmost terrain tile starting a `width` tiles wide stretch of ground with `height` free
+    /// cells above all of it
+    pub fn open_ground(&mut self, width: i32, height: i32) -> Vec2 {
+        let solid = self.solid_cells();
+        let mut terrain = self.tile_positions(TileKind::Terrain);
+        terrain.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+        terrain
+            .into_iter()
+            .find(|tile| {
+                (0..width).all(|right| {
+                    let ground = cell(*tile) + IVec2::new(right, 0);
+                    solid.contains(&ground)
+                        && (1..=height).all(|above| !solid.contains(&(ground + IVec2::Y * above)))
+                })
+            })
+            .unwrap_or_else(|| panic!("no ground {} wide with {} free above", width, height))
+    }
+
+    /// World space translations of every entity with that component
+    pub fn translations<C: Component>(&mut self) -> Vec<Vec2> {
+        let mut q_entity = self.app.world.query_filtered::<&GlobalTransform, With<C>>();
+        q_entity
+            .iter(&self.app.world)
+            .map(|transform| transform.translation().truncate())
+            .collect()
+    }
+
+    pub fn level_bounds(&mut self, identifier: &str) -> Option<Rect> {
+        let world = &mut self.app.world;
+        let mut q_level = world.query::<(&Handle<LdtkLevel>, &GlobalTransform)>();
+        let ldtk_levels = world.resource::<Assets<LdtkLevel>>();
+        q_level.iter(world).find_map(|(handle, transform)| {
+            let level = &ldtk_levels.get(handle)?.level;
+            let min = transform.translation().truncate();
+            (level.identifier == identifier).then(|| {
+                Rect::from_corners(
+                    min,
+                    min + Vec2::new(level.px_wid as f32, level.px_hei as f32),
+                )
+            })
+        })
+    }
+
+    /// Move every player there at rest
+    pub fn teleport_players(&mut self, translation: Vec2) {
+        let mut q_player = self
+            .app
+            .world
+            .query_filtered::<(&mut Transform, &mut Velocity), With<Player>>();
+        q_player.for_each_mut(&mut self.app.world, |(mut transform, mut velocity)| {
+            transform.translation = translation.extend(transform.translation.z);
+            *velocity = Velocity::zero();
+        });
+    }
+
+    /// Move one player there at rest
+    pub fn teleport_player(&mut self, index: usize, translation: Vec2) {
+        let mut q_player = self
+            .app
+            .world
+            .query::<(&Player, &mut Transform, &mut Velocity)>();
+        q_player.for_each_mut(
+            &mut self.app.world,
+            |(player, mut transform, mut velocity)| {
+                if player.index == index {
+                    transform.translation = translation.extend(transform.translation.z);
+                    *velocity = Velocity::zero();
+                }
+            },
+        );
+    }
+
+    /// Info of the first player
+    pub fn player_info(&mut self) -> &PlayerInfo {
+        let mut q_player = self.app.world.query::<(&Player, &PlayerInfo)>();
+        q_player
+            .iter(&self.app.world)
+            .find_map(|(player, info)| (player.index == 0).then_some(info))
+            .expect("no first player")
+    }
+
+    pub fn player_translation(&mut self, index: usize) -> Vec2 {
+        let mut q_player = self.app.world.query::<(&Player, &Transform)>();
+        q_player
+            .iter(&self.app.world)
+            .find_map(|(player, transform)| {
+                (player.index == index).then(|| transform.translation.truncate())
+            })
+            .unwrap_or_else(|| panic!("no player {}", index))
+    }
+}
+
+pub fn cell(position: Vec2) -> IVec2 {
+    (position / TILE_SIZE).floor().as_ivec2()
+}
@@ -0,0 +1,27 @@
+use bevy::prelude::*;
+
+use crate::{
+    data::enemy::Enemy,
+    plugins::{
+        enemy::EnemyEvent,
+        player::{GameMode, PlayerEvent},
+    },
+};
+
+use super::{TestApp, TILE_SIZE};
+
+#[test]
+fn enemy_chases_and_kills_player() {
+    let mut test = TestApp::new(GameMode::Classic).in_level("Test_Enemy");
+    test.wait_for_level();
+
+    // The enemy stands on the floor without patrol points until it sees a player
+    let enemy = test.translations::<Enemy>()[0];
+    test.teleport_players(enemy - 5.0 * TILE_SIZE * Vec2::X);
+
+    let spotted =
+        test.step_until_event(60, |ev: &EnemyEvent| matches!(ev, EnemyEvent::Spotted(..)));
+    assert!(spotted, "the enemy never saw the player next to it");
+    let died = test.step_until_event(300, |ev: &PlayerEvent| matches!(ev, PlayerEvent::Died(_)));
+    assert!(died, "the enemy never reached the player");
+}
@@ -0,0 +1,23 @@
+use bevy::prelude::*;
+
+use crate::{data::physics::ComplexExternalForce, plugins::physics::FIXED_TIMESTEP};
+
+#[test]
+fn decaying_force_channel_fades_out() {
+    let mut cef = ComplexExternalForce::default();
+    let kept = cef.register("kept");
+    let fading = cef.register_with_decay("fading", 0.75);
+    cef.set(kept, Vec2::X);
+    cef.set(fading, Vec2::Y);
+
+    // Half a second at 75% lost per second leaves half the force
+    (0..30).for_each(|_| cef.decay(FIXED_TIMESTEP));
+
+    assert_eq!(cef.get(kept), Vec2::X);
+    assert!(
+        (cef.get(fading) - 0.5 * Vec2::Y).length() < 1e-4,
+        "fading force is {} after half a second",
+        cef.get(fading)
+    );
+    assert!((cef.composite_force() - Vec2::new(1.0, 0.5)).length() < 1e-4);
+}
@@ -0,0 +1,32 @@
+use bevy::prelude::*;
+
+use crate::{
+    data::platform::MovingBody,
+    plugins::player::{GameMode, PlayerEvent},
+};
+
+use super::{TestApp, TILE_SIZE};
+
+#[test]
+fn moving_platform_carries_grounded_player() {
+    let mut test = TestApp::new(GameMode::Health).in_level("Test_Platform");
+    test.wait_for_level();
+
+    // The platform floats over the floor and heads right first
+    let platform = test.translations::<MovingBody>()[0];
+    test.teleport_players(platform + 2.0 * TILE_SIZE * Vec2::Y);
+
+    let landed = test.step_until_event(120, |ev: &PlayerEvent| {
+        matches!(ev, PlayerEvent::Grounded(_))
+    });
+    assert!(landed, "player never landed on the platform");
+    let start = test.player_translation(0);
+    test.step(30);
+
+    let moved = test.player_translation(0) - start;
+    assert!(
+        moved.x > TILE_SIZE,
+        "player standing still on the platform only moved {}",
+        moved
+    );
+}
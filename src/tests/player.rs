@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+
+use crate::{
+    data::{
+        player::{PlayerAbilities, PlayerHealth, WebUpgrade},
+        tilemap::TileKind,
+    },
+    plugins::{
+        player::{GameMode, PlayerEvent, WebEvent, WebSettings},
+        tilemap::SHARP_SPIKE_DAMAGE,
+    },
+};
+
+use super::{cell, TestApp, TILE_SIZE};
+
+/// Drop a player from a few tiles above a spike of the spikes test level, standing on the ground
+/// or floating in the air, and see whether it dies
+fn dies_falling_on_spike(kind: TileKind, on_ground: bool) -> bool {
+    let mut test = TestApp::new(GameMode::Classic).in_level("Test_Spikes");
+    test.wait_for_level();
+
+    let solid = test.solid_cells();
+    let spike = test
+        .tile_positions(kind)
+        .into_iter()
+        .find(|spike| solid.contains(&(cell(*spike) - IVec2::Y)) == on_ground)
+        .unwrap_or_else(|| panic!("no {:?} in the spikes test level", kind));
+    test.teleport_players(spike + 3.0 * TILE_SIZE * Vec2::Y);
+
+    test.step_until_event(90, |ev: &PlayerEvent| matches!(ev, PlayerEvent::Died(_)))
+}
+
+#[test]
+fn player_is_grounded_after_falling() {
+    let mut test = TestApp::new(GameMode::Health);
+    test.wait_for_level();
+
+    let ground = test.open_ground(1, 6);
+    test.teleport_players(ground + 4.0 * TILE_SIZE * Vec2::Y);
+
+    let landed = test.step_until_event(120, |ev: &PlayerEvent| {
+        matches!(ev, PlayerEvent::Grounded(_))
+    });
+    assert!(landed, "player never landed on {}", ground);
+    assert!(test.player_info().is_grounded);
+}
+
+#[test]
+fn player_dies_on_spike() {
+    assert!(
+        dies_falling_on_spike(TileKind::SpikeUp, true),
+        "player fell on a spike on the ground and lived"
+    );
+}
+
+#[test]
+fn spike_only_hurts_from_its_pointed_side() {
+    assert!(
+        dies_falling_on_spike(TileKind::SpikeUp, false),
+        "player fell on the tip of a spike and lived"
+    );
+    assert!(
+        !dies_falling_on_spike(TileKind::SpikeDown, false),
+        "player died landing on the base of a spike"
+    );
+}
+
+#[test]
+fn trap_hurts_again_once_invulnerability_ends() {
+    let mut test = TestApp::new(GameMode::Health).in_level("Test_Spikes");
+    test.wait_for_level();
+
+    // Without knockback the player stays on the spikes after the first hit
+    let mut q_health = test.app.world.query::<&mut PlayerHealth>();
+    q_health.for_each_mut(&mut test.app.world, |mut health| {
+        health.knockback = Vec2::ZERO;
+    });
+    let mut bed = test.tile_positions(TileKind::SharpSpikeUp);
+    bed.sort_by(|a, b| a.x.total_cmp(&b.x));
+    test.teleport_players(bed[bed.len() / 2] + 2.0 * TILE_SIZE * Vec2::Y);
+
+    let hurt = test.step_until_event(60, |ev: &PlayerEvent| matches!(ev, PlayerEvent::Hurt(_)));
+    assert!(hurt, "player fell on sharp spikes unharmed");
+    let health = q_health.single(&test.app.world);
+    assert_eq!(health.current, health.max - SHARP_SPIKE_DAMAGE);
+
+    let died = test.step_until_event(90, |ev: &PlayerEvent| matches!(ev, PlayerEvent::Died(_)));
+    assert!(
+        died,
+        "player stayed on the spikes without getting hurt again"
+    );
+}
+
+#[test]
+fn players_move_with_their_own_input() {
+    let mut test = TestApp::with_players(GameMode::Health, 2);
+    test.wait_for_level();
+
+    // Room above the ground and to its right for the second player to walk into
+    let ground = test.open_ground(4, 3);
+    test.teleport_players(ground + 2.0 * TILE_SIZE * Vec2::Y);
+    test.step(60);
+
+    let start = [test.player_translation(0), test.player_translation(1)];
+    test.inputs[1].right = true;
+    test.step(30);
+
+    assert!(
+        test.player_translation(1).x > start[1].x + TILE_SIZE,
+        "second player didn't walk right from {}",
+        start[1]
+    );
+    assert!(
+        test.player_translation(0).distance(start[0]) < 0.5,
+        "first player moved from {} without input",
+        start[0]
+    );
+}
+
+/// Have the second player shoot a web at the first one standing this many tiles away, and see
+/// whether it tethers them
+fn tethers_player_tiles_away(
+    tiles: f32,
+    max_rope_length: f32,
+    upgrade: Option<WebUpgrade>,
+) -> bool {
+    let mut test = TestApp::with_players(GameMode::Health, 2);
+    test.app.world.resource_mut::<WebSettings>().max_rope_length = max_rope_length;
+    test.wait_for_level();
+    if let Some(upgrade) = upgrade {
+        let mut q_abilities = test.app.world.query::<&mut PlayerAbilities>();
+        q_abilities.for_each_mut(&mut test.app.world, |mut abilities| {
+            abilities.unlock(upgrade);
+        });
+    }
+
+    // Both players on flat ground a few tiles apart
+    let ground = test.open_ground(tiles as i32 + 2, 3);
+    test.teleport_player(0, ground + 2.0 * TILE_SIZE * Vec2::Y);
+    test.teleport_player(1, ground + Vec2::new(tiles * TILE_SIZE, 2.0 * TILE_SIZE));
+    test.step(60);
+
+    // The body collider sits a bit below the center of the sprite
+    test.inputs[1].cursor = Some(test.player_translation(0) - 3.0 * Vec2::Y);
+    test.inputs[1].shoot_pressed = true;
+    test.step(1);
+    test.inputs[1].shoot_pressed = false;
+
+    test.step_until_event(60, |ev: &WebEvent| matches!(ev, WebEvent::Tethered { .. }))
+}
+
+#[test]
+fn web_tethers_the_other_player() {
+    assert!(
+        tethers_player_tiles_away(6.0, WebSettings::default().max_rope_length, None),
+        "the second player's web never stuck to the first player"
+    );
+}
+
+#[test]
+fn web_landing_out_of_rope_reach_does_not_hold() {
+    assert!(
+        !tethers_player_tiles_away(6.0, 3.0 * TILE_SIZE, None),
+        "a web landing further than the longest rope tethered the players"
+    );
+}
+
+#[test]
+fn long_range_web_holds_past_the_default_rope() {
+    // Further than the default rope and range, within their long range scale
+    let max_rope_length = WebSettings::default().max_rope_length;
+    let tiles = (max_rope_length * 1.2 / TILE_SIZE).ceil();
+    assert!(
+        !tethers_player_tiles_away(tiles, max_rope_length, None),
+        "a web tethered a player further than the default rope"
+    );
+    assert!(
+        tethers_player_tiles_away(tiles, max_rope_length, Some(WebUpgrade::LongRange)),
+        "a long range web landing past the default rope didn't hold"
+    );
+}
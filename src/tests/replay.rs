@@ -0,0 +1,72 @@
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{
+    plugins::{
+        player::{GameMode, PlayerInput},
+        replay::{InputRecording, ReplayMode, ReplayPlugin, ReplayRun, FINAL_POSITION_TOLERANCE},
+    },
+    GameState,
+};
+
+use super::TestApp;
+
+fn with_replay(mode: ReplayMode) -> TestApp {
+    let mut test = TestApp::new(GameMode::Health);
+    test.app
+        .add_plugin(ReplayPlugin::new(mode, Some(GameState::InGame)));
+    test.wait_for_level();
+    test
+}
+
+#[test]
+fn replay_ends_where_the_recording_did() {
+    let path = std::env::temp_dir().join(format!("replay-test-{}.txt", std::process::id()));
+
+    let mut record = with_replay(ReplayMode::Record(path.clone()));
+    let started = (0..60).any(|_| {
+        record.step(1);
+        record.app.world.resource::<ReplayRun>().is_running()
+    });
+    assert!(started, "recorded run never started");
+    record.inputs[0] = PlayerInput {
+        right: true,
+        ..default()
+    };
+    record.step(20);
+    record.inputs[0] = PlayerInput {
+        right: true,
+        jump: true,
+        jump_pressed: true,
+        ..default()
+    };
+    record.step(1);
+    record.inputs[0] = PlayerInput {
+        left: true,
+        jump: true,
+        ..default()
+    };
+    record.step(30);
+    record.inputs[0] = PlayerInput::default();
+    record.step(30);
+
+    let recording = record.app.world.resource::<InputRecording>().clone();
+    let expected = recording
+        .final_translation
+        .expect("recording has no final position");
+    recording.save(&path).expect("failed to save the recording");
+
+    let mut replay = with_replay(ReplayMode::Replay {
+        path: path.clone(),
+        headless: true,
+    });
+    // Checking the final position panics on a mismatch
+    let finished = replay.step_until_event(recording.ticks.len() as u32 + 60, |_: &AppExit| true);
+    let _ = std::fs::remove_file(&path);
+    assert!(finished, "replay never finished");
+    assert!(
+        replay.player_translation(0).distance(expected) <= FINAL_POSITION_TOLERANCE,
+        "replay ended at {} but the recording ended at {}",
+        replay.player_translation(0),
+        expected
+    );
+}
@@ -0,0 +1,158 @@
+use bevy::prelude::*;
+
+use crate::{
+    data::{
+        enemy::{Enemy, EnemyKind, PatrolPath},
+        platform::MovingBody,
+        player::WebUpgrade,
+        tilemap::{
+            CoopDoor, CoopSwitch, TileKind, Trap, WebGrabbable, WebSwitch, WebUpgradePickup,
+        },
+    },
+    plugins::{
+        player::{GameMode, PlayerEvent},
+        tilemap::{LevelChanged, LevelStats, LevelStreaming, TileEvent},
+    },
+};
+
+use super::{TestApp, TILE_SIZE};
+
+#[test]
+fn entering_next_level_sends_level_changed() {
+    let mut test = TestApp::new(GameMode::Health);
+    test.wait_for_level();
+
+    let next_level = test
+        .level_bounds("Level_1")
+        .expect("Level_1 wasn't spawned next to Level_0");
+    test.teleport_players(next_level.center());
+
+    let changed =
+        test.step_until_event(10, |ev: &LevelChanged| ev.previous == 0 && ev.current == 1);
+    assert!(changed, "no level change after moving into Level_1");
+}
+
+#[test]
+fn level_budget_despawns_farther_levels() {
+    let mut test = TestApp::new(GameMode::Health);
+    test.wait_for_level();
+    assert!(
+        test.level_bounds("Level_1").is_some(),
+        "the neighbouring level isn't spawned"
+    );
+
+    test.app.insert_resource(LevelStreaming {
+        max_levels: 1,
+        ..default()
+    });
+    test.step(5);
+
+    assert!(
+        test.level_bounds("Level_1").is_none(),
+        "the neighbouring level is still spawned"
+    );
+    let stats = *test.app.world.resource::<LevelStats>();
+    assert_eq!(stats.levels, 1);
+    assert!(stats.colliders > 0, "the selected level lost its colliders");
+}
+
+#[test]
+fn crumbling_tile_falls_away_and_comes_back() {
+    let mut test = TestApp::new(GameMode::Health).in_level("Test_Tiles");
+    test.wait_for_level();
+
+    let tile = test.tile_positions(TileKind::Crumbling)[0];
+    test.teleport_players(tile + 2.0 * TILE_SIZE * Vec2::Y);
+
+    let crumbled =
+        test.step_until_event(120, |ev: &TileEvent| matches!(ev, TileEvent::Crumbled(_)));
+    assert!(crumbled, "the tile never gave way under the player");
+    let restored =
+        test.step_until_event(300, |ev: &TileEvent| matches!(ev, TileEvent::Restored(_)));
+    assert!(restored, "the tile never came back");
+}
+
+#[test]
+fn falling_player_breaks_tile() {
+    let mut test = TestApp::new(GameMode::Health).in_level("Test_Tiles");
+    test.wait_for_level();
+
+    let tile = test.tile_positions(TileKind::Breakable)[0];
+    test.teleport_players(tile + 8.0 * TILE_SIZE * Vec2::Y);
+
+    let broke = test.step_until_event(120, |ev: &TileEvent| matches!(ev, TileEvent::Broke(_)));
+    assert!(broke, "the player landed on the tile without breaking it");
+}
+
+#[test]
+fn conveyor_carries_standing_player() {
+    let mut test = TestApp::new(GameMode::Health).in_level("Test_Tiles");
+    test.wait_for_level();
+
+    // Drop the player near the start of the belt, it carries players right
+    let mut belt = test.tile_positions(TileKind::ConveyorRight);
+    belt.sort_by(|a, b| a.x.total_cmp(&b.x));
+    test.teleport_players(belt[1] + 2.0 * TILE_SIZE * Vec2::Y);
+
+    let landed = test.step_until_event(120, |ev: &PlayerEvent| {
+        matches!(ev, PlayerEvent::Grounded(_))
+    });
+    assert!(landed, "player never landed on the conveyor");
+    let start = test.player_translation(0);
+    test.step(30);
+
+    let moved = test.player_translation(0) - start;
+    assert!(
+        moved.x > TILE_SIZE / 2.0,
+        "player standing still on the conveyor only moved {}",
+        moved
+    );
+}
+
+#[test]
+fn ldtk_entities_spawn_with_their_fields() {
+    let mut test = TestApp::new(GameMode::Health).in_level("Test_Entities");
+    test.wait_for_level();
+    let world = &mut test.app.world;
+
+    let mut damages: Vec<i32> = world
+        .query::<&Trap>()
+        .iter(world)
+        .map(|trap| trap.damage)
+        .collect();
+    damages.sort();
+    assert_eq!(
+        damages,
+        [2, 3, 4, 5],
+        "hazards and the enemy lost their damage"
+    );
+
+    let (enemy, path) = world.query::<(&Enemy, &PatrolPath)>().single(world);
+    assert_eq!(enemy.kind, EnemyKind::Flyer);
+    assert_eq!(enemy.sight_range, 0.0);
+    assert_eq!(path.points.len(), 2, "the patrol point wasn't read");
+
+    let mut speeds: Vec<f32> = world
+        .query::<&MovingBody>()
+        .iter(world)
+        .map(|body| body.speed)
+        .collect();
+    speeds.sort_by(f32::total_cmp);
+    assert_eq!(speeds, [15.0, 20.0, 40.0]);
+
+    assert_eq!(world.query::<&CoopSwitch>().single(world).channel, 1);
+    assert_eq!(world.query::<&CoopDoor>().single(world).channel, 1);
+    assert_eq!(
+        world.query::<&WebUpgradePickup>().single(world).upgrade,
+        WebUpgrade::FastShot
+    );
+    assert_eq!(world.query::<&WebSwitch>().iter(world).count(), 1);
+    assert_eq!(
+        world
+            .query_filtered::<(), (With<WebGrabbable>, Without<WebSwitch>)>()
+            .iter(world)
+            .count(),
+        1,
+        "the crate wasn't spawned"
+    );
+}
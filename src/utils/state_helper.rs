@@ -1,6 +1,11 @@
-use bevy::{ecs::schedule::StateData, prelude::*};
+use bevy::{
+    ecs::schedule::{StateData, SystemLabelId},
+    prelude::*,
+};
 use iyes_loopless::{condition::ConditionalSystemDescriptor, prelude::*};
 
+use crate::plugins::physics::{FixedUpdateStage, FIXED_UPDATE};
+
 pub trait StateExtend {
     fn add_system_run_if<Params, State: StateData>(
         &mut self,
@@ -13,6 +18,23 @@ pub trait StateExtend {
         state: Option<State>,
         system: impl IntoSystem<(), (), Params>,
     ) -> &mut Self;
+
+    /// Run the system every physics tick instead of every frame
+    fn add_fixed_system_run_if<Params, State: StateData>(
+        &mut self,
+        state: Option<State>,
+        stage: FixedUpdateStage,
+        system: impl IntoSystem<(), (), Params>,
+    ) -> &mut Self;
+
+    /// Run systems every physics tick one after the other, in the order they're added to the
+    /// chain. Bevy runs the systems of a stage in any order from one tick to the next, so two
+    /// unordered systems touching the same data break replays.
+    fn add_fixed_chain_run_if<State: StateData>(
+        &mut self,
+        state: Option<State>,
+        stage: FixedUpdateStage,
+    ) -> FixedChain<'_, State>;
 }
 impl StateExtend for App {
     fn add_system_run_if<Params, State: StateData>(
@@ -36,6 +58,60 @@ impl StateExtend for App {
             None => self.add_startup_system(system),
         }
     }
+
+    fn add_fixed_system_run_if<Params, State: StateData>(
+        &mut self,
+        state: Option<State>,
+        stage: FixedUpdateStage,
+        system: impl IntoSystem<(), (), Params>,
+    ) -> &mut Self {
+        self.add_fixed_timestep_system(
+            FIXED_UPDATE,
+            stage as usize,
+            system.run_in_some_state(state),
+        )
+    }
+
+    fn add_fixed_chain_run_if<State: StateData>(
+        &mut self,
+        state: Option<State>,
+        stage: FixedUpdateStage,
+    ) -> FixedChain<'_, State> {
+        FixedChain {
+            app: self,
+            state,
+            stage,
+            previous: None,
+        }
+    }
+}
+
+/// Systems of a fixed stage, see [`StateExtend::add_fixed_chain_run_if`]
+pub struct FixedChain<'a, State: StateData> {
+    app: &'a mut App,
+    state: Option<State>,
+    stage: FixedUpdateStage,
+    previous: Option<SystemLabelId>,
+}
+
+impl<'a, State: StateData> FixedChain<'a, State> {
+    /// Run the system after the one added before it, `label` has to be unique in the stage
+    pub fn then<Params>(
+        mut self,
+        label: impl SystemLabel,
+        system: impl IntoSystem<(), (), Params>,
+    ) -> Self {
+        let label = label.as_label();
+        let system = system.run_in_some_state(self.state.clone()).label(label);
+        let system = match self.previous {
+            Some(previous) => system.after(previous),
+            None => system,
+        };
+        self.app
+            .add_fixed_timestep_system(FIXED_UPDATE, self.stage as usize, system);
+        self.previous = Some(label);
+        self
+    }
 }
 
 pub trait RunIfExtend<Params>: IntoSystem<(), (), Params> + Sized {